@@ -0,0 +1,16 @@
+#![no_main]
+
+use beef_messages::BeefMessage;
+use libfuzzer_sys::fuzz_target;
+
+// whatever a client sends, decoding it must not panic, and anything that isn't NotBeef
+// has to encode back to what was sent, minus the bytes that were ignored at the end
+fuzz_target!(|data: &[u8]| {
+    let decoded = BeefMessage::from(data.to_vec());
+    if decoded == BeefMessage::NotBeef {
+        return;
+    }
+    let encoded: Vec<u8> = decoded.clone().into();
+    assert!(data.starts_with(&encoded));
+    assert_eq!(BeefMessage::from(encoded), decoded);
+});
@@ -10,10 +10,16 @@
 //! ```
 //! Every message contains command bytes, and - if needed for the command - the client_id (`c_id`),
 //! and the payload - which can be variable in length. We don't need any lengths as the structure
-//! for each command is known and well-defined.
+//! for each command is known and well-defined. Tournament commands carry a tournament id in place
+//! of the client_id.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 
 pub type Payload = Vec<u8>;
 pub type ClientId = u16;
+pub type TournamentId = u16;
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum BeefMessage {
     /// Lists all users online
@@ -24,10 +30,47 @@ pub enum BeefMessage {
     BattleGuess(Payload),
     /// Forfeits the current battle
     BattleForfeit,
-    /// Sends [Payload] to player guessing words
+    /// Sends [Payload] to the other player in the current battle
     Message(Payload),
+    /// Sends [Payload] to everyone online
+    Lobby(Payload),
+    /// Sends [Payload] to user with id [ClientId] only
+    Whisper(ClientId, Payload),
     /// Gracefully disconnect from server
     Disconnect,
+    /// Lists recently finished battles
+    History,
+    /// Starts a practice battle against a word picked by the server
+    Practice,
+    /// Starts or resumes the daily challenge, the same word for everyone
+    Daily,
+    /// Lists tournaments
+    Tournaments,
+    /// Registers for the tournament with id [TournamentId]
+    TournamentJoin(TournamentId),
+    /// Offers or accepts a rematch of the last battle with roles swapped, whoever guessed last
+    /// picks the word [Payload]
+    Rematch(Payload),
+    /// Adds user with id [ClientId] to the friend list
+    Friend(ClientId),
+    /// Adds user with id [ClientId] to the block list, they can't challenge, message or spectate
+    /// you anymore
+    Block(ClientId),
+    /// Removes user with id [ClientId] from both the friend and the block list
+    Forget(ClientId),
+    /// Lists friends, the ones online first
+    FriendList,
+    /// Watches the current battle of user with id [ClientId] until it's over, refused if they or
+    /// their opponent blocked you
+    Spectate(ClientId),
+    /// Sets presence to available, away or dnd, optionally followed by a status message, all as
+    /// text in [Payload]
+    SetStatus(Payload),
+    /// Checks whether the other side is still there, answered with [BeefMessage::Pong]. The server
+    /// sends it as a `beef: PING` line
+    Ping,
+    /// Answers [BeefMessage::Ping]
+    Pong,
     /// Malformed commands
     NotBeef,
 }
@@ -38,7 +81,7 @@ impl From<Vec<u8>> for BeefMessage {
             return BeefMessage::NotBeef;
         };
 
-        return match value.first().unwrap() {
+        match value.first().unwrap() {
             0x6c => BeefMessage::List,
             0x62 => {
                 let length = value.len();
@@ -67,8 +110,57 @@ impl From<Vec<u8>> for BeefMessage {
                 }
                 BeefMessage::Message(value[1..length].to_vec())
             }
+            0x61 => {
+                if value.len() < 2 {
+                    return BeefMessage::NotBeef;
+                }
+                BeefMessage::Lobby(value[1..].to_vec())
+            }
+            0x77 => {
+                if value.len() < 4 {
+                    return BeefMessage::NotBeef;
+                }
+                BeefMessage::Whisper(parse_client_id((value[1], value[2])), value[3..].to_vec())
+            }
+            0x68 => BeefMessage::History,
+            0x70 => BeefMessage::Practice,
+            0x63 => BeefMessage::Daily,
+            0x74 => BeefMessage::Tournaments,
+            0x6a => {
+                if value.len() < 3 {
+                    return BeefMessage::NotBeef;
+                }
+                BeefMessage::TournamentJoin(parse_client_id((value[1], value[2])))
+            }
+            0x72 => BeefMessage::Rematch(value[1..].to_vec()),
+            0x46 | 0x42 | 0x55 => {
+                if value.len() < 3 {
+                    return BeefMessage::NotBeef;
+                }
+                let id = parse_client_id((value[1], value[2]));
+                match value[0] {
+                    0x46 => BeefMessage::Friend(id),
+                    0x42 => BeefMessage::Block(id),
+                    _ => BeefMessage::Forget(id),
+                }
+            }
+            0x4c => BeefMessage::FriendList,
+            0x76 => {
+                if value.len() < 3 {
+                    return BeefMessage::NotBeef;
+                }
+                BeefMessage::Spectate(parse_client_id((value[1], value[2])))
+            }
+            0x69 => BeefMessage::Ping,
+            0x6f => BeefMessage::Pong,
+            0x73 => {
+                if value.len() < 2 {
+                    return BeefMessage::NotBeef;
+                }
+                BeefMessage::SetStatus(value[1..].to_vec())
+            }
             _ => BeefMessage::NotBeef,
-        };
+        }
     }
 }
 
@@ -101,6 +193,73 @@ impl From<BeefMessage> for Vec<u8> {
                 command.append(&mut payload.to_vec());
                 command
             }
+            BeefMessage::Lobby(payload) => {
+                let mut command: Vec<u8> = vec![0x61];
+                command.append(&mut payload.to_vec());
+                command
+            }
+            BeefMessage::Whisper(id, payload) => {
+                let mut command: Vec<u8> = vec![0x77];
+                command.extend_from_slice(&id.to_be_bytes());
+                command.append(&mut payload.to_vec());
+                command
+            }
+            BeefMessage::History => {
+                vec![0x68]
+            }
+            BeefMessage::Practice => {
+                vec![0x70]
+            }
+            BeefMessage::Daily => {
+                vec![0x63]
+            }
+            BeefMessage::Tournaments => {
+                vec![0x74]
+            }
+            BeefMessage::TournamentJoin(id) => {
+                let mut command: Vec<u8> = vec![0x6a];
+                command.extend_from_slice(&id.to_be_bytes());
+                command
+            }
+            BeefMessage::Rematch(payload) => {
+                let mut command: Vec<u8> = vec![0x72];
+                command.append(&mut payload.to_vec());
+                command
+            }
+            BeefMessage::Friend(id) => {
+                let mut command: Vec<u8> = vec![0x46];
+                command.extend_from_slice(&id.to_be_bytes());
+                command
+            }
+            BeefMessage::Block(id) => {
+                let mut command: Vec<u8> = vec![0x42];
+                command.extend_from_slice(&id.to_be_bytes());
+                command
+            }
+            BeefMessage::Forget(id) => {
+                let mut command: Vec<u8> = vec![0x55];
+                command.extend_from_slice(&id.to_be_bytes());
+                command
+            }
+            BeefMessage::FriendList => {
+                vec![0x4c]
+            }
+            BeefMessage::Spectate(id) => {
+                let mut command: Vec<u8> = vec![0x76];
+                command.extend_from_slice(&id.to_be_bytes());
+                command
+            }
+            BeefMessage::SetStatus(payload) => {
+                let mut command: Vec<u8> = vec![0x73];
+                command.append(&mut payload.to_vec());
+                command
+            }
+            BeefMessage::Ping => {
+                vec![0x69]
+            }
+            BeefMessage::Pong => {
+                vec![0x6f]
+            }
             BeefMessage::NotBeef => {
                 vec![0xff]
             }
@@ -111,10 +270,14 @@ impl From<BeefMessage> for Vec<u8> {
 impl BeefMessage {
     pub fn get_payload(&self) -> Option<Payload> {
         match self {
-            BeefMessage::BattleInit(_, p) => { Some(p.clone())}
-            BeefMessage::BattleGuess(p) => { Some(p.clone())}
-            BeefMessage::Message(p) => { Some(p.clone())}
-            _ => { None }
+            BeefMessage::BattleInit(_, p) => Some(p.clone()),
+            BeefMessage::BattleGuess(p) => Some(p.clone()),
+            BeefMessage::Message(p) => Some(p.clone()),
+            BeefMessage::Lobby(p) => Some(p.clone()),
+            BeefMessage::Whisper(_, p) => Some(p.clone()),
+            BeefMessage::SetStatus(p) => Some(p.clone()),
+            BeefMessage::Rematch(p) => Some(p.clone()),
+            _ => None,
         }
     }
 }
@@ -124,10 +287,142 @@ fn parse_client_id(client_id_bytes: (u8, u8)) -> u16 {
     ((client_id_bytes.0 as u16) << 8) | client_id_bytes.1 as u16
 }
 
+/// Wordle-like feedback for a guess, as the server sends it after `beef: WRONG GUESS! `:
+/// uppercase letters are in the right spot, lowercase letters are somewhere else in the word and
+/// underscores aren't in the word at all
+pub fn hint(target: &str, guess: &str) -> String {
+    let target: Vec<char> = target.trim().to_lowercase().chars().collect();
+    let guess: Vec<char> = guess.trim().to_lowercase().chars().collect();
+    // letters not matched in place, each one can only be "somewhere else" once
+    let mut unmatched: Vec<char> = target
+        .iter()
+        .enumerate()
+        .filter(|(i, letter)| guess.get(*i) != Some(letter))
+        .map(|(_, letter)| *letter)
+        .collect();
+    guess
+        .iter()
+        .enumerate()
+        .flat_map(|(i, letter)| {
+            if target.get(i) == Some(letter) {
+                return letter.to_uppercase().collect::<Vec<char>>();
+            }
+            match unmatched.iter().position(|other| other == letter) {
+                Some(position) => {
+                    unmatched.remove(position);
+                    vec![*letter]
+                }
+                None => vec!['_'],
+            }
+        })
+        .collect()
+}
+
+/// Escapes text for a JSON string: quotes, backslashes and control characters, nothing else.
+/// Names, words and messages all come from players, so whatever goes out as JSON goes through this
+pub fn escape_json(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '"' => "\\\"".to_string(),
+            '\\' => "\\\\".to_string(),
+            c if c.is_control() => format!("\\u{:04x}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// What a client is being rate limited on
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Limited {
+    Guesses,
+    Chat,
+    Challenges,
+}
+
+impl Limited {
+    fn name(&self) -> &'static str {
+        match self {
+            Limited::Guesses => "GUESSES",
+            Limited::Chat => "MESSAGES",
+            Limited::Challenges => "CHALLENGES",
+        }
+    }
+}
+
+/// Errors the server replies with as a `beef: ` line, so that clients can tell them apart from
+/// everything else it says
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BeefError {
+    /// Too many [Limited] commands, the next one goes through after the [Duration]
+    SlowDown(Limited, Duration),
+    /// Kept going after being told to slow down, the server hangs up
+    Flooding,
+    /// Every slot on the server is taken, the server hangs up
+    ServerFull,
+}
+
+impl fmt::Display for BeefError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BeefError::SlowDown(limited, retry) => write!(
+                f,
+                "beef: SLOW DOWN! TOO MANY {}, TRY AGAIN IN {}ms",
+                limited.name(),
+                retry.as_millis()
+            ),
+            BeefError::Flooding => write!(f, "beef: TOO MANY COMMANDS, DISCONNECTING"),
+            BeefError::ServerFull => write!(f, "beef: SERVER FULL, TRY AGAIN LATER"),
+        }
+    }
+}
+
+impl FromStr for BeefError {
+    type Err = ();
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        for error in [BeefError::Flooding, BeefError::ServerFull] {
+            if line == error.to_string() {
+                return Ok(error);
+            }
+        }
+        let (name, retry) = line
+            .strip_prefix("beef: SLOW DOWN! TOO MANY ")
+            .and_then(|rest| rest.strip_suffix("ms"))
+            .and_then(|rest| rest.split_once(", TRY AGAIN IN "))
+            .ok_or(())?;
+        let limited = [Limited::Guesses, Limited::Chat, Limited::Challenges]
+            .into_iter()
+            .find(|limited| limited.name() == name)
+            .ok_or(())?;
+        let retry = retry.parse().map_err(|_| ())?;
+        Ok(BeefError::SlowDown(limited, Duration::from_millis(retry)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn hint_marks_letters() {
+        assert_eq!(hint("crane", "cater"), "Ca_er");
+        assert_eq!(hint("crane", "crane"), "CRANE");
+        assert_eq!(hint("crane", "ghost"), "_____");
+    }
+
+    #[test]
+    fn escape_json_leaves_the_rest_alone() {
+        assert_eq!(escape_json("say \"hi\"\\\n"), "say \\\"hi\\\"\\\\\\u000a");
+        assert_eq!(escape_json("crane 🡒"), "crane 🡒");
+    }
+
+    #[test]
+    fn hint_counts_repeated_letters_once() {
+        assert_eq!(hint("crane", "eerie"), "__r_E");
+        assert_eq!(hint("abbey", "babes"), "baBE_");
+    }
+
     #[test]
     fn simple_ser_deser() {
         let beef_msg = BeefMessage::List;
@@ -150,6 +445,40 @@ mod tests {
         let beef_ser_deser: BeefMessage = beef_ser.clone().into();
         assert_eq!(beef_msg, beef_ser_deser);
 
+        let beef_msg = BeefMessage::History;
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Practice;
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Daily;
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Tournaments;
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::FriendList;
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Ping;
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Pong;
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
     }
 
     #[test]
@@ -163,6 +492,27 @@ mod tests {
         let beef_ser: Vec<u8> = beef_msg.clone().into();
         let beef_ser_deser: BeefMessage = beef_ser.clone().into();
         assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Lobby([0xabu8, 0xaau8].to_vec());
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::SetStatus(b"away out for lunch".to_vec());
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        // the word is optional, only the one picking it sends one
+        let beef_msg = BeefMessage::Rematch([0xabu8, 0xaau8].to_vec());
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Rematch(Vec::new());
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
     }
 
     #[test]
@@ -171,5 +521,57 @@ mod tests {
         let beef_ser: Vec<u8> = beef_msg.clone().into();
         let beef_ser_deser: BeefMessage = beef_ser.clone().into();
         assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Whisper(0x1234u16, [0xabu8, 0xaau8].to_vec());
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::TournamentJoin(0x1234u16);
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Friend(0x1234u16);
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Block(0x1234u16);
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Forget(0x1234u16);
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+
+        let beef_msg = BeefMessage::Spectate(0x1234u16);
+        let beef_ser: Vec<u8> = beef_msg.clone().into();
+        let beef_ser_deser: BeefMessage = beef_ser.clone().into();
+        assert_eq!(beef_msg, beef_ser_deser);
+    }
+
+    #[test]
+    fn error_to_line_and_back() {
+        let error = BeefError::SlowDown(Limited::Chat, Duration::from_millis(1500));
+        assert_eq!(
+            error.to_string(),
+            "beef: SLOW DOWN! TOO MANY MESSAGES, TRY AGAIN IN 1500ms"
+        );
+        assert_eq!(error.to_string().parse(), Ok(error));
+        assert_eq!(
+            BeefError::Flooding.to_string().parse(),
+            Ok(BeefError::Flooding)
+        );
+        assert_eq!(
+            BeefError::ServerFull.to_string().parse(),
+            Ok(BeefError::ServerFull)
+        );
+        assert_eq!(
+            "beef: GUESS CORRECT, BEEF SQUASHED!".parse::<BeefError>(),
+            Err(())
+        );
     }
 }
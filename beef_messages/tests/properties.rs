@@ -0,0 +1,200 @@
+// the encoding as these tests pin it down, checked against generated messages and byte strings:
+// - every message starts with a command byte of its own
+// - ids are two bytes, big endian, right after it
+// - the payload is whatever follows, its length is never sent
+// - a payload needs at least one byte, except a rematch's
+// - bytes after a command without a payload are ignored
+// - anything else, an empty message too, decodes to NotBeef, which encodes to 0xff
+
+use std::collections::{HashMap, HashSet};
+use std::mem::discriminant;
+
+use beef_messages::BeefMessage;
+
+const CASES: usize = 10_000;
+// one case for each variant, see arbitrary_message
+const VARIANTS: usize = 23;
+
+// xorshift, plenty random for picking test cases and the same ones on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn id(&mut self) -> u16 {
+        self.next() as u16
+    }
+
+    // mostly short, sometimes long, never empty unless asked to
+    fn bytes(&mut self, min: usize) -> Vec<u8> {
+        let max = if self.below(10) == 0 { 1024 } else { 16 };
+        let length = min + self.below(max);
+        (0..length).map(|_| self.next() as u8).collect()
+    }
+}
+
+fn arbitrary_message(rng: &mut Rng) -> BeefMessage {
+    // an empty rematch is how whoever doesn't pick accepts, it comes up as often as a variant
+    if rng.below(VARIANTS + 1) == VARIANTS {
+        return BeefMessage::Rematch(Vec::new());
+    }
+    match rng.below(VARIANTS) {
+        0 => BeefMessage::List,
+        1 => BeefMessage::BattleInit(rng.id(), rng.bytes(1)),
+        2 => BeefMessage::BattleGuess(rng.bytes(1)),
+        3 => BeefMessage::BattleForfeit,
+        4 => BeefMessage::Message(rng.bytes(1)),
+        5 => BeefMessage::Lobby(rng.bytes(1)),
+        6 => BeefMessage::Whisper(rng.id(), rng.bytes(1)),
+        7 => BeefMessage::Disconnect,
+        8 => BeefMessage::History,
+        9 => BeefMessage::Practice,
+        10 => BeefMessage::Daily,
+        11 => BeefMessage::Tournaments,
+        12 => BeefMessage::TournamentJoin(rng.id()),
+        13 => BeefMessage::Rematch(rng.bytes(0)),
+        14 => BeefMessage::Friend(rng.id()),
+        15 => BeefMessage::Block(rng.id()),
+        16 => BeefMessage::Forget(rng.id()),
+        17 => BeefMessage::FriendList,
+        18 => BeefMessage::SetStatus(rng.bytes(1)),
+        19 => BeefMessage::Ping,
+        20 => BeefMessage::Pong,
+        21 => BeefMessage::Spectate(rng.id()),
+        _ => BeefMessage::NotBeef,
+    }
+}
+
+// random bytes, but often starting with a real command byte so the decoder gets past the first match
+fn arbitrary_bytes(rng: &mut Rng) -> Vec<u8> {
+    let mut bytes = rng.bytes(0);
+    if !bytes.is_empty() && rng.below(2) == 0 {
+        let message: Vec<u8> = arbitrary_message(rng).into();
+        bytes[0] = message[0];
+    }
+    bytes
+}
+
+fn encode(message: &BeefMessage) -> Vec<u8> {
+    message.clone().into()
+}
+
+#[test]
+fn generator_covers_every_variant() {
+    let mut rng = Rng(0x5eed);
+    let seen: HashSet<_> = (0..CASES)
+        .map(|_| discriminant(&arbitrary_message(&mut rng)))
+        .collect();
+    assert_eq!(seen.len(), VARIANTS);
+}
+
+#[test]
+fn every_message_survives_a_round_trip() {
+    let mut rng = Rng(0xbeef);
+    for _ in 0..CASES {
+        let message = arbitrary_message(&mut rng);
+        let decoded = BeefMessage::from(encode(&message));
+        assert_eq!(decoded, message);
+    }
+}
+
+#[test]
+fn command_bytes_are_unique() {
+    let mut rng = Rng(0xc0de);
+    let mut commands = HashMap::new();
+    for _ in 0..CASES {
+        let message = arbitrary_message(&mut rng);
+        let command = encode(&message)[0];
+        let previous = commands.insert(command, discriminant(&message));
+        assert!(previous.is_none_or(|previous| previous == discriminant(&message)));
+    }
+}
+
+#[test]
+fn ids_are_big_endian_after_the_command() {
+    let id = 0xa03f;
+    for message in [
+        BeefMessage::BattleInit(id, b"crane".to_vec()),
+        BeefMessage::Whisper(id, b"psst".to_vec()),
+        BeefMessage::TournamentJoin(id),
+        BeefMessage::Friend(id),
+        BeefMessage::Block(id),
+        BeefMessage::Forget(id),
+        BeefMessage::Spectate(id),
+    ] {
+        assert_eq!(encode(&message)[1..3], [0xa0, 0x3f]);
+    }
+}
+
+#[test]
+fn decoding_never_panics_and_is_canonical() {
+    let mut rng = Rng(0xf00d);
+    for _ in 0..CASES {
+        let bytes = arbitrary_bytes(&mut rng);
+        let decoded = BeefMessage::from(bytes.clone());
+        if decoded == BeefMessage::NotBeef {
+            continue;
+        }
+        // re-encoding gives back what was sent, minus anything that was ignored at the end
+        let encoded = encode(&decoded);
+        assert!(bytes.starts_with(&encoded), "{bytes:?} became {encoded:?}");
+        assert_eq!(BeefMessage::from(encoded), decoded);
+    }
+}
+
+#[test]
+fn not_beef_encodes_to_ff() {
+    assert_eq!(encode(&BeefMessage::NotBeef), vec![0xff]);
+    assert_eq!(BeefMessage::from(vec![0xff]), BeefMessage::NotBeef);
+    assert_eq!(BeefMessage::from(Vec::new()), BeefMessage::NotBeef);
+}
+
+#[test]
+fn missing_payloads_and_ids_are_not_beef() {
+    for bytes in [
+        // a challenge needs a word, a guess, a chat or a status something to say
+        &b"b\x00\x01"[..],
+        b"g",
+        b"m",
+        b"a",
+        b"w\x00\x01",
+        b"s",
+        // ids are two bytes, one isn't enough
+        b"b\x00",
+        b"w\x00",
+        b"j\x00",
+        b"F\x00",
+        b"B\x00",
+        b"U\x00",
+        b"v\x00",
+    ] {
+        assert_eq!(
+            BeefMessage::from(bytes.to_vec()),
+            BeefMessage::NotBeef,
+            "{bytes:?}"
+        );
+    }
+    // a rematch is fine without a word, when it's not your pick
+    assert_eq!(
+        BeefMessage::from(b"r".to_vec()),
+        BeefMessage::Rematch(Vec::new())
+    );
+}
+
+#[test]
+fn trailing_bytes_after_fixed_commands_are_ignored() {
+    assert_eq!(BeefMessage::from(b"l\r\n".to_vec()), BeefMessage::List);
+    assert_eq!(
+        BeefMessage::from(b"j\x00\x07extra".to_vec()),
+        BeefMessage::TournamentJoin(7)
+    );
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+
+use beef_messages::BeefMessage;
+
+use crate::ClientId;
+
+pub const HELP: &str = "\
+/list                 list everyone online
+/beef <user> <word>   beef with a user, they have to guess the word (? lets a bot pick it)
+/guess <word>         guess the word in your current beef
+/forfeit              give up the current beef
+/say <text>           talk to whoever you're beefing with
+/quit                 disconnect and leave
+/help                 show this
+anything not starting with / is sent as it is, e.g. p to practice or a<text> to talk to everyone.
+tab completes commands and the ids of users seen so far.";
+
+// every command with how it's used, in the order /help lists them
+const COMMANDS: [(&str, &str); 7] = [
+    ("/list", "/list"),
+    ("/beef", "/beef <user> <word>"),
+    ("/guess", "/guess <word>"),
+    ("/forfeit", "/forfeit"),
+    ("/say", "/say <text>"),
+    ("/quit", "/quit"),
+    ("/help", "/help"),
+];
+
+// commands that take an id right after the command byte
+const TAKES_ID: &[u8] = b"bwjFBUv";
+
+// a line typed into the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    List,
+    Beef(ClientId, String),
+    Guess(String),
+    Forfeit,
+    Say(String),
+    Quit,
+    Help,
+    // the old way, a command byte and whatever follows
+    Raw(BeefMessage),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Unknown(String),
+    // the usage of the command that's missing something
+    Usage(&'static str),
+    NotAnId(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Unknown(command) => {
+                write!(f, "no command {command}, /help lists them")
+            }
+            CommandError::Usage(usage) => write!(f, "usage: {usage}"),
+            CommandError::NotAnId(id) => {
+                write!(
+                    f,
+                    "{id} is not a user id, ids are up to 4 hex digits like a03f"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, CommandError> {
+        let line = line.trim();
+        let Some(slashed) = line.strip_prefix('/') else {
+            return parse_typed_command(line.as_bytes()).map(|bytes| Command::Raw(bytes.into()));
+        };
+        let (name, rest) = slashed.split_once(' ').unwrap_or((slashed, ""));
+        let rest = rest.trim();
+        let usage = usage_of(name).ok_or_else(|| CommandError::Unknown(format!("/{name}")))?;
+        let needs = |text: &str| match text.is_empty() {
+            true => Err(CommandError::Usage(usage)),
+            false => Ok(text.to_string()),
+        };
+        let no_arguments = |command| match rest.is_empty() {
+            true => Ok(command),
+            false => Err(CommandError::Usage(usage)),
+        };
+
+        match name {
+            "list" => no_arguments(Command::List),
+            "forfeit" => no_arguments(Command::Forfeit),
+            "quit" => no_arguments(Command::Quit),
+            "help" => Ok(Command::Help),
+            "guess" if !rest.contains(' ') => Ok(Command::Guess(needs(rest)?)),
+            "say" => Ok(Command::Say(needs(rest)?)),
+            "beef" => {
+                let (user, word) = rest.split_once(' ').ok_or(CommandError::Usage(usage))?;
+                let word = word.trim();
+                if word.contains(' ') {
+                    return Err(CommandError::Usage(usage));
+                }
+                Ok(Command::Beef(parse_id(user)?, needs(word)?))
+            }
+            _ => Err(CommandError::Usage(usage)),
+        }
+    }
+
+    // what to send the server, help isn't sent anywhere
+    pub fn to_message(&self) -> Option<BeefMessage> {
+        Some(match self {
+            Command::List => BeefMessage::List,
+            Command::Beef(to, word) => BeefMessage::BattleInit(*to, word.as_bytes().to_vec()),
+            Command::Guess(word) => BeefMessage::BattleGuess(word.as_bytes().to_vec()),
+            Command::Forfeit => BeefMessage::BattleForfeit,
+            Command::Say(text) => BeefMessage::Message(text.as_bytes().to_vec()),
+            Command::Quit => BeefMessage::Disconnect,
+            Command::Help => return None,
+            Command::Raw(msg) => msg.clone(),
+        })
+    }
+}
+
+fn usage_of(name: &str) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .find(|(command, _)| command[1..] == *name)
+        .map(|(_, usage)| *usage)
+}
+
+pub fn parse_id(id: &str) -> Result<ClientId, CommandError> {
+    let is_hex = !id.is_empty() && id.len() <= 4 && id.chars().all(|c| c.is_ascii_hexdigit());
+    match is_hex {
+        true => Ok(ClientId::from_str_radix(id, 16).unwrap()),
+        false => Err(CommandError::NotAnId(id.to_string())),
+    }
+}
+
+// parse literal into two bytes (e.g. a03f -> xa0 x3f) for ClientId or TournamentId
+pub fn parse_literal_into_byte(literal: &[u8; 4]) -> Option<[u8; 2]> {
+    let literal = std::str::from_utf8(literal).ok()?;
+    let id = parse_id(literal).ok()?;
+    Some(id.to_be_bytes())
+}
+
+// what was typed, with the id after commands that take one turned into its two bytes
+pub fn parse_typed_command(typed: &[u8]) -> Result<Vec<u8>, CommandError> {
+    let mut bytes = typed.to_vec();
+    if let Some((first, rest)) = typed.split_first() {
+        if TAKES_ID.contains(first) {
+            let Some(literal) = rest.first_chunk::<4>() else {
+                return Err(CommandError::NotAnId(
+                    String::from_utf8_lossy(rest).to_string(),
+                ));
+            };
+            let id = parse_literal_into_byte(literal).ok_or_else(|| {
+                CommandError::NotAnId(String::from_utf8_lossy(literal).to_string())
+            })?;
+            bytes.splice(1..5, id);
+        }
+    }
+    Ok(bytes)
+}
+
+// whole lines the one typed so far could be completed to, commands first and then user ids
+pub fn complete(typed: &str, ids: impl IntoIterator<Item = ClientId>) -> Vec<String> {
+    let Some((command, user)) = typed.split_once(' ') else {
+        if !typed.starts_with('/') {
+            return Vec::new();
+        }
+        return COMMANDS
+            .iter()
+            .filter(|(command, _)| command.starts_with(typed))
+            .map(|(command, _)| format!("{command} "))
+            .collect();
+    };
+    // only /beef takes a user, and only as its first argument
+    if command != "/beef" || user.contains(' ') {
+        return Vec::new();
+    }
+    ids.into_iter()
+        .map(|id| format!("{id:04x}"))
+        .filter(|id| id.starts_with(user))
+        .map(|id| format!("{command} {id} "))
+        .collect()
+}
+
+// as much as all completions have in common, to fill in when there's more than one
+pub fn common_prefix(completions: &[String]) -> String {
+    let Some(first) = completions.first() else {
+        return String::new();
+    };
+    let mut prefix = first.as_str();
+    for completion in completions {
+        while !completion.starts_with(prefix) {
+            prefix = &prefix[..prefix.len() - prefix.chars().last().map_or(0, char::len_utf8)];
+        }
+    }
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(Command::parse("/list"), Ok(Command::List));
+        assert_eq!(
+            Command::parse("/beef a03f apple"),
+            Ok(Command::Beef(0xa03f, "apple".to_string()))
+        );
+        assert_eq!(
+            Command::parse("/beef 7 ?"),
+            Ok(Command::Beef(7, "?".to_string()))
+        );
+        assert_eq!(
+            Command::parse("/guess  crane "),
+            Ok(Command::Guess("crane".to_string()))
+        );
+        assert_eq!(
+            Command::parse("/say good luck"),
+            Ok(Command::Say("good luck".to_string()))
+        );
+        assert_eq!(Command::parse("p"), Ok(Command::Raw(BeefMessage::Practice)));
+        assert_eq!(
+            Command::parse("w00ffpsst"),
+            Ok(Command::Raw(BeefMessage::Whisper(0xff, b"psst".to_vec())))
+        );
+    }
+
+    #[test]
+    fn bad_commands_say_what_is_wrong() {
+        assert_eq!(
+            Command::parse("/beef a03g apple"),
+            Err(CommandError::NotAnId("a03g".to_string()))
+        );
+        assert_eq!(
+            Command::parse("/beef a03f"),
+            Err(CommandError::Usage("/beef <user> <word>"))
+        );
+        assert_eq!(
+            Command::parse("/guess two words"),
+            Err(CommandError::Usage("/guess <word>"))
+        );
+        assert_eq!(
+            Command::parse("/list everyone"),
+            Err(CommandError::Usage("/list"))
+        );
+        assert_eq!(
+            Command::parse("/dance"),
+            Err(CommandError::Unknown("/dance".to_string()))
+        );
+        // invalid hex used to turn into whatever the fallback made of it
+        assert_eq!(
+            Command::parse("bzzzzapple"),
+            Err(CommandError::NotAnId("zzzz".to_string()))
+        );
+        assert_eq!(
+            Command::parse("j01"),
+            Err(CommandError::NotAnId("01".to_string()))
+        );
+    }
+
+    #[test]
+    fn typed_ids_become_two_bytes() {
+        assert_eq!(parse_literal_into_byte(b"a03f"), Some([0xa0, 0x3f]));
+        assert_eq!(parse_literal_into_byte(b"A03F"), Some([0xa0, 0x3f]));
+        assert_eq!(parse_literal_into_byte(b"a0 f"), None);
+        assert_eq!(
+            parse_typed_command(b"ba03fapple"),
+            Ok(b"b\xa0\x3fapple".to_vec())
+        );
+        assert_eq!(parse_typed_command(b"j0001"), Ok(b"j\x00\x01".to_vec()));
+        assert_eq!(parse_typed_command(b"gapple"), Ok(b"gapple".to_vec()));
+    }
+
+    #[test]
+    fn completes_commands_and_ids() {
+        let ids = [0xa03f, 0xa100, 0x0007];
+        assert_eq!(complete("/g", ids), vec!["/guess "]);
+        assert_eq!(complete("/", ids).len(), COMMANDS.len());
+        assert_eq!(complete("/beef a", ids), vec!["/beef a03f ", "/beef a100 "]);
+        assert_eq!(complete("/beef 0", ids), vec!["/beef 0007 "]);
+        assert!(complete("/beef a03f app", ids).is_empty());
+        assert!(complete("/guess a", ids).is_empty());
+        assert!(complete("hello", ids).is_empty());
+        assert_eq!(
+            common_prefix(&complete("/beef a", ids)),
+            "/beef a".to_string()
+        );
+        assert_eq!(common_prefix(&complete("/f", ids)), "/forfeit ".to_string());
+    }
+}
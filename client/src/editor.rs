@@ -0,0 +1,387 @@
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use beef_messages::BeefMessage;
+use wguessr_client::command::common_prefix;
+use wguessr_client::ClientId;
+
+use crate::tui::Panes;
+
+const PROMPT: &str = "> ";
+const CTRL_C: u8 = 3;
+const CTRL_D: u8 = 4;
+const BACKSPACE: u8 = 8;
+const TAB: u8 = b'\t';
+const ESCAPE: u8 = 27;
+const DELETE: u8 = 127;
+// enough to scroll back through a session
+const MAX_HISTORY: usize = 100;
+
+// the terminal the client is typed into, shared with whoever prints so the line being typed can
+// be put back underneath anything that comes in
+#[derive(Clone)]
+pub struct Terminal {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    input: Input,
+    // what stty said before switching to raw mode, None when not switched
+    saved: Option<String>,
+    // the full screen, None when lines just scroll by
+    panes: Option<Panes>,
+}
+
+// the line being typed and the ones typed before it
+#[derive(Default)]
+struct Input {
+    typed: Vec<u8>,
+    history: Vec<String>,
+    // how far back up arrow went, and what was typed before going back
+    browsing: Option<(usize, Vec<u8>)>,
+}
+
+impl Input {
+    fn typed(&self) -> String {
+        String::from_utf8_lossy(&self.typed).to_string()
+    }
+
+    fn submit(&mut self) -> String {
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.typed)).to_string();
+        self.browsing = None;
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        line
+    }
+
+    fn back(&mut self) {
+        let (at, draft) = match self.browsing.take() {
+            Some((at, draft)) => (at.saturating_sub(1), draft),
+            None if self.history.is_empty() => return,
+            None => (self.history.len() - 1, std::mem::take(&mut self.typed)),
+        };
+        self.typed = self.history[at].clone().into_bytes();
+        self.browsing = Some((at, draft));
+    }
+
+    fn forward(&mut self) {
+        let Some((at, draft)) = self.browsing.take() else {
+            return;
+        };
+        if at + 1 < self.history.len() {
+            self.typed = self.history[at + 1].clone().into_bytes();
+            self.browsing = Some((at + 1, draft));
+        } else {
+            self.typed = draft;
+        }
+    }
+
+    fn backspace(&mut self) {
+        // a whole character, not just its last byte
+        while let Some(byte) = self.typed.pop() {
+            if byte & 0b1100_0000 != 0b1000_0000 {
+                break;
+            }
+        }
+    }
+}
+
+impl Terminal {
+    // takes over the terminal, keys come in one by one instead of after enter, and with
+    // full_screen set the whole screen is drawn as panes. anything that isn't a terminal, like a
+    // pipe, is read line by line as before
+    pub fn new(me: ClientId, full_screen: bool) -> Self {
+        let saved = io::stdin()
+            .is_terminal()
+            .then(|| stty(&["-g"]))
+            .flatten()
+            .filter(|_| stty(&["-icanon", "-echo", "-isig", "min", "1"]).is_some());
+        let panes = (saved.is_some() && full_screen).then(|| {
+            // the alternate screen, so whatever was on the terminal is back afterwards
+            print!("\x1b[?1049h");
+            Panes::new(me)
+        });
+        let terminal = Terminal {
+            state: Arc::new(Mutex::new(State {
+                input: Input::default(),
+                saved,
+                panes,
+            })),
+        };
+        terminal.redraw(true);
+        terminal
+    }
+
+    // prints a line from the server above the one being typed, or into its pane
+    pub fn print(&self, line: &str) {
+        self.show(line, Panes::update);
+    }
+
+    // prints anything else, like errors and help
+    pub fn note(&self, line: &str) {
+        self.show(line, Panes::message);
+    }
+
+    fn show(&self, line: &str, into_panes: fn(&mut Panes, &str)) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(panes) = state.panes.as_mut() {
+            into_panes(panes, line);
+            drop(state);
+            self.redraw(true);
+            return;
+        }
+        let mut stdout = io::stdout().lock();
+        if state.saved.is_none() {
+            let _ = writeln!(stdout, "{line}");
+            return;
+        }
+        let _ = write!(stdout, "\r\x1b[K{}\r\n", line.replace('\n', "\r\n"));
+        let _ = write!(stdout, "{PROMPT}{}", state.input.typed());
+        let _ = stdout.flush();
+    }
+
+    // tells the panes what was sent, the replies make more sense with it
+    pub fn sent(&self, msg: &BeefMessage) {
+        if let Some(panes) = self.state.lock().unwrap().panes.as_mut() {
+            panes.sent(msg);
+        }
+    }
+
+    // shows the panes as they are on the new connection, signed in as me
+    pub fn reconnected(&self, me: ClientId) {
+        if let Some(panes) = self.state.lock().unwrap().panes.as_mut() {
+            panes.reconnected(me);
+        }
+        self.redraw(true);
+    }
+
+    // the next line typed, None once there's nothing more to read or ctrl-c/ctrl-d was pressed.
+    // tab asks complete for whole lines the one typed so far could become
+    pub fn read_line(&self, complete: impl Fn(&str) -> Vec<String>) -> io::Result<Option<String>> {
+        if self.state.lock().unwrap().saved.is_none() {
+            let mut line = String::new();
+            return match io::stdin().read_line(&mut line)? {
+                0 => Ok(None),
+                _ => Ok(Some(line.trim_end_matches(['\r', '\n']).to_string())),
+            };
+        }
+
+        let mut stdin = io::stdin().lock();
+        let mut byte = [0];
+        // typed right after a lone escape, so read already
+        let mut pending = None;
+        loop {
+            let key = match pending.take() {
+                Some(key) => key,
+                None if stdin.read(&mut byte)? == 0 => return Ok(None),
+                None => byte[0],
+            };
+            match key {
+                b'\r' | b'\n' => {
+                    let line = self.edit(Input::submit);
+                    if self.state.lock().unwrap().panes.is_none() {
+                        print!("\r\n");
+                    }
+                    self.redraw(false);
+                    return Ok(Some(line));
+                }
+                CTRL_C => return Ok(None),
+                CTRL_D if self.edit(|input| input.typed.is_empty()) => return Ok(None),
+                TAB => self.complete(&complete),
+                // up and down go through what was typed before
+                ESCAPE => match escape_sequence(|| read_soon(&mut stdin))? {
+                    Escaped::Up => self.edit(Input::back),
+                    Escaped::Down => self.edit(Input::forward),
+                    Escaped::Other => {}
+                    Escaped::Lone(key) => pending = key,
+                },
+                BACKSPACE | DELETE => self.edit(Input::backspace),
+                key if key >= b' ' => self.edit(|input| input.typed.push(key)),
+                _ => {}
+            }
+            self.redraw(false);
+        }
+    }
+
+    fn edit<T>(&self, edit: impl FnOnce(&mut Input) -> T) -> T {
+        edit(&mut self.state.lock().unwrap().input)
+    }
+
+    // fills in as much as every completion agrees on, and shows them when there's a choice
+    fn complete(&self, complete: impl Fn(&str) -> Vec<String>) {
+        let typed = self.edit(|input| input.typed());
+        let completions = complete(&typed);
+        if completions.len() > 1 {
+            self.note(&completions.join("  "));
+        }
+        let completed = common_prefix(&completions);
+        if completed.len() > typed.len() {
+            self.edit(|input| input.typed = completed.into_bytes());
+        }
+    }
+
+    // the prompt, or with everything the whole screen when something other than it changed
+    fn redraw(&self, everything: bool) {
+        let state = self.state.lock().unwrap();
+        if state.saved.is_none() {
+            return;
+        }
+        let typed = state.input.typed();
+        let mut stdout = io::stdout().lock();
+        match &state.panes {
+            Some(panes) if everything => {
+                let (rows, cols) = size();
+                let _ = write!(stdout, "{}", panes.render(rows, cols, &typed));
+            }
+            Some(_) => {
+                let (rows, _) = size();
+                let _ = write!(stdout, "\x1b[{rows};1H{PROMPT}{typed}\x1b[K");
+            }
+            None => {
+                let _ = write!(stdout, "\r\x1b[K{PROMPT}{typed}");
+            }
+        }
+        let _ = stdout.flush();
+    }
+
+    // puts the terminal back the way it was, has to happen before leaving
+    pub fn restore(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.panes.take().is_some() {
+            print!("\x1b[?1049l");
+        }
+        if let Some(saved) = state.saved.take() {
+            print!("\r\x1b[K");
+            let _ = io::stdout().flush();
+            stty(&[&saved]);
+        }
+    }
+}
+
+enum Escaped {
+    Up,
+    Down,
+    // some other key's sequence, which the editor has no use for
+    Other,
+    // esc on its own, maybe with whatever was typed next
+    Lone(Option<u8>),
+}
+
+// terminals send a key's whole sequence at once, so anything that isn't there right away was typed
+fn escape_sequence(mut soon: impl FnMut() -> io::Result<Option<u8>>) -> io::Result<Escaped> {
+    match soon()? {
+        Some(b'[') => {}
+        key => return Ok(Escaped::Lone(key)),
+    }
+    // parameters like the 3 in delete's `[3~` come first, the sequence ends on a byte from @ to ~
+    loop {
+        match soon()? {
+            Some(b'A') => return Ok(Escaped::Up),
+            Some(b'B') => return Ok(Escaped::Down),
+            Some(0x40..=0x7e) | None => return Ok(Escaped::Other),
+            Some(_) => {}
+        }
+    }
+}
+
+// the next byte if it comes within a tenth of a second, reads block again afterwards
+fn read_soon(stdin: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    stty(&["min", "0", "time", "1"]);
+    let read = stdin.read(&mut byte);
+    stty(&["min", "1", "time", "0"]);
+    Ok((read? > 0).then_some(byte[0]))
+}
+
+// rows and columns of the terminal, or what terminals used to be when stty can't tell
+fn size() -> (usize, usize) {
+    stty(&["size"])
+        .and_then(|size| {
+            let (rows, cols) = size.split_once(' ')?;
+            Some((rows.parse().ok()?, cols.parse().ok()?))
+        })
+        .unwrap_or((24, 80))
+}
+
+// stty works on whatever terminal its stdin is, so it gets ours
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lone_escape_keeps_the_next_key() {
+        let sequence = |bytes: &[u8]| {
+            let mut bytes = bytes.iter().copied();
+            escape_sequence(|| Ok(bytes.next())).unwrap()
+        };
+        assert!(matches!(sequence(b"[A"), Escaped::Up));
+        assert!(matches!(sequence(b"[B"), Escaped::Down));
+        assert!(matches!(sequence(b"[C"), Escaped::Other));
+        assert!(matches!(sequence(b""), Escaped::Lone(None)));
+        assert!(matches!(sequence(b"l"), Escaped::Lone(Some(b'l'))));
+    }
+
+    #[test]
+    fn longer_sequences_are_read_to_the_end() {
+        // delete, home and page up, with a key typed after each that has to be left alone
+        for bytes in [&b"[3~x"[..], b"[1~x", b"[5~x", b"[1;5Cx"] {
+            let mut bytes = bytes.iter().copied();
+            assert!(matches!(
+                escape_sequence(|| Ok(bytes.next())).unwrap(),
+                Escaped::Other
+            ));
+            assert_eq!(bytes.next(), Some(b'x'));
+        }
+    }
+
+    #[test]
+    fn up_and_down_go_through_history() {
+        let mut input = Input::default();
+        for line in ["/list", "/guess crane", "/guess crane"] {
+            input.typed = line.as_bytes().to_vec();
+            input.submit();
+        }
+        // the same line twice in a row is kept once
+        assert_eq!(input.history, ["/list", "/guess crane"]);
+
+        input.typed = b"/be".to_vec();
+        input.back();
+        assert_eq!(input.typed(), "/guess crane");
+        input.back();
+        input.back();
+        assert_eq!(input.typed(), "/list");
+        input.forward();
+        assert_eq!(input.typed(), "/guess crane");
+        // back to what was being typed
+        input.forward();
+        assert_eq!(input.typed(), "/be");
+    }
+
+    #[test]
+    fn backspace_takes_whole_characters() {
+        let mut input = Input {
+            typed: "/say hé".as_bytes().to_vec(),
+            ..Input::default()
+        };
+        input.backspace();
+        assert_eq!(input.typed(), "/say h");
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+use std::io;
+
+use crate::ClientId;
+
+// what can go wrong talking to the server: the connection itself, or the server saying no
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    // the server turned us away, with the line it said it in
+    Refused(String),
+    // the server said something we didn't expect at this point
+    Unexpected(String),
+    // logging back in found the account gone, the server was restarted since
+    NoAccount(ClientId),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(error) => write!(f, "{error}"),
+            ClientError::Refused(line) => write!(f, "refused: {line}"),
+            ClientError::Unexpected(line) => write!(f, "unexpected reply: {line}"),
+            ClientError::NoAccount(id) => write!(f, "the server has no account {id:x}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(error: io::Error) -> Self {
+        ClientError::Io(error)
+    }
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
@@ -0,0 +1,266 @@
+use beef_messages::escape_json;
+
+use crate::ClientId;
+
+// a line from the server, picked apart where there's something in it a bot would act on.
+// everything else comes through as it was sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // someone picked a word for us to guess
+    Challenged(ClientId),
+    // our challenge went through, the other side is guessing now
+    BattleStarted(ClientId),
+    WordLength(usize),
+    // our guess was wrong, with the hint for every letter
+    WrongGuess(String),
+    // the other side guessed this, and it was wrong
+    OpponentGuessed(String),
+    GuessesLeft(usize),
+    Squashed,
+    Forfeited,
+    OpponentForfeited,
+    OpponentLeft(ClientId),
+    Chat { from: ClientId, text: String },
+    Lobby { from: ClientId, text: String },
+    Whisper { from: ClientId, text: String },
+    // the header of the user list, the users come after it one per line
+    UsersOnline,
+    // one user in a list, with whatever is known about them
+    Listed { id: ClientId, details: String },
+    Pong,
+    ShutDown,
+    Line(String),
+}
+
+impl Event {
+    pub fn parse(line: &str) -> Event {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let Some(beef) = line.strip_prefix("beef: ") else {
+            return parse_talk(line)
+                .or_else(|| parse_listed(line))
+                .unwrap_or_else(|| Event::Line(line.to_string()));
+        };
+        let id_between = |prefix: &str, suffix: &str| {
+            beef.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(suffix))
+                .and_then(parse_id)
+        };
+        let number_between = |prefix: &str, suffix: &str| {
+            beef.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(suffix))
+                .and_then(|number| number.parse().ok())
+        };
+
+        if let Some(id) = id_between("USER ", " HAS BEEF WITH YOU!") {
+            Event::Challenged(id)
+        } else if let Some(id) = id_between("STARTING BEEF WITH USER ", "!") {
+            Event::BattleStarted(id)
+        } else if let Some(id) = id_between("USER ", " LEFT, BEEF OVER!") {
+            Event::OpponentLeft(id)
+        } else if let Some(letters) = number_between("THE WORD HAS ", " LETTERS") {
+            Event::WordLength(letters)
+        } else if let Some(left) = number_between("", " GUESSES LEFT") {
+            Event::GuessesLeft(left)
+        } else if let Some(hint) = beef.strip_prefix("WRONG GUESS! ") {
+            Event::WrongGuess(hint.to_string())
+        } else if let Some(guess) = beef.strip_prefix("WRONG GUESS ") {
+            Event::OpponentGuessed(guess.to_string())
+        } else {
+            match beef {
+                "GUESS CORRECT, BEEF SQUASHED!" => Event::Squashed,
+                "BEEF FORFEITED!" => Event::Forfeited,
+                "OPPONENT FORFEITED!" => Event::OpponentForfeited,
+                "USERS ONLINE:" => Event::UsersOnline,
+                "PONG" => Event::Pong,
+                "SERVER SHUT DOWN, BYE!" => Event::ShutDown,
+                _ => Event::Line(line.to_string()),
+            }
+        }
+    }
+
+    // what scripts call it, and the name it goes by in json
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Challenged(_) => "challenged",
+            Event::BattleStarted(_) => "battle_started",
+            Event::WordLength(_) => "word_length",
+            Event::WrongGuess(_) => "wrong_guess",
+            Event::OpponentGuessed(_) => "opponent_guessed",
+            Event::GuessesLeft(_) => "guesses_left",
+            Event::Squashed => "squashed",
+            Event::Forfeited => "forfeited",
+            Event::OpponentForfeited => "opponent_forfeited",
+            Event::OpponentLeft(_) => "opponent_left",
+            Event::Chat { .. } => "chat",
+            Event::Lobby { .. } => "lobby",
+            Event::Whisper { .. } => "whisper",
+            Event::UsersOnline => "users_online",
+            Event::Listed { .. } => "listed",
+            Event::Pong => "pong",
+            Event::ShutDown => "shut_down",
+            Event::Line(_) => "line",
+        }
+    }
+
+    // one line of json, with what was picked out of the line and the line itself
+    pub fn to_json(&self, line: &str) -> String {
+        let id = |id: &ClientId| format!("\"{id:04x}\"");
+        let text = |text: &str| format!("\"{}\"", escape_json(text));
+        let fields: Vec<(&str, String)> = match self {
+            Event::Challenged(opponent)
+            | Event::BattleStarted(opponent)
+            | Event::OpponentLeft(opponent) => vec![("id", id(opponent))],
+            Event::WordLength(letters) => vec![("letters", letters.to_string())],
+            Event::WrongGuess(hint) => vec![("hint", text(hint))],
+            Event::OpponentGuessed(guess) => vec![("guess", text(guess))],
+            Event::GuessesLeft(left) => vec![("left", left.to_string())],
+            Event::Chat { from, text: said }
+            | Event::Lobby { from, text: said }
+            | Event::Whisper { from, text: said } => {
+                vec![("from", id(from)), ("text", text(said))]
+            }
+            Event::Listed { id: user, details } => {
+                vec![("id", id(user)), ("details", text(details))]
+            }
+            _ => Vec::new(),
+        };
+        let mut json = format!("{{\"event\":\"{}\"", self.kind());
+        for (name, value) in fields {
+            json.push_str(&format!(",\"{name}\":{value}"));
+        }
+        json.push_str(&format!(",\"line\":{}}}", text(line)));
+        json
+    }
+}
+
+// "a03f: hi", "[lobby] a03f: hi" or "[whisper] a03f: hi"
+fn parse_talk(line: &str) -> Option<Event> {
+    let (kind, rest) = match line.split_once("] ") {
+        Some((kind, rest)) => (kind.strip_prefix('[')?, rest),
+        None => ("", line),
+    };
+    let (from, text) = rest.split_once(": ")?;
+    let from = parse_id(from)?;
+    let text = text.to_string();
+    match kind {
+        "" => Some(Event::Chat { from, text }),
+        "lobby" => Some(Event::Lobby { from, text }),
+        "whisper" => Some(Event::Whisper { from, text }),
+        _ => None,
+    }
+}
+
+// "a03f (bot, easy, available)", in user and friend lists
+fn parse_listed(line: &str) -> Option<Event> {
+    let (id, details) = line.split_once(" (")?;
+    let details = details.strip_suffix(')')?.to_string();
+    if id.len() != 4 {
+        return None;
+    }
+    Some(Event::Listed {
+        id: parse_id(id)?,
+        details,
+    })
+}
+
+fn parse_id(id: &str) -> Option<ClientId> {
+    ClientId::from_str_radix(id, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battle_lines_are_picked_apart() {
+        assert_eq!(
+            Event::parse("beef: USER a03f HAS BEEF WITH YOU!\r\n"),
+            Event::Challenged(0xa03f)
+        );
+        assert_eq!(
+            Event::parse("beef: STARTING BEEF WITH USER 7!"),
+            Event::BattleStarted(7)
+        );
+        assert_eq!(
+            Event::parse("beef: THE WORD HAS 5 LETTERS"),
+            Event::WordLength(5)
+        );
+        assert_eq!(
+            Event::parse("beef: WRONG GUESS! _+__+"),
+            Event::WrongGuess("_+__+".to_string())
+        );
+        assert_eq!(
+            Event::parse("beef: WRONG GUESS peach"),
+            Event::OpponentGuessed("peach".to_string())
+        );
+        assert_eq!(Event::parse("beef: 3 GUESSES LEFT"), Event::GuessesLeft(3));
+        assert_eq!(
+            Event::parse("beef: GUESS CORRECT, BEEF SQUASHED!"),
+            Event::Squashed
+        );
+    }
+
+    #[test]
+    fn talk_is_told_apart_by_where_it_came_from() {
+        let text = "hi: there".to_string();
+        assert_eq!(
+            Event::parse("a03f: hi: there"),
+            Event::Chat {
+                from: 0xa03f,
+                text: text.clone()
+            }
+        );
+        assert_eq!(
+            Event::parse("[lobby] a03f: hi: there"),
+            Event::Lobby {
+                from: 0xa03f,
+                text: text.clone()
+            }
+        );
+        assert_eq!(
+            Event::parse("[whisper] a03f: hi: there"),
+            Event::Whisper { from: 0xa03f, text }
+        );
+    }
+
+    #[test]
+    fn users_in_lists_are_picked_apart() {
+        assert_eq!(Event::parse("beef: USERS ONLINE:"), Event::UsersOnline);
+        assert_eq!(
+            Event::parse("7b69 (bot, easy, available)"),
+            Event::Listed {
+                id: 0x7b69,
+                details: "bot, easy, available".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn events_become_json() {
+        let line = "beef: THE WORD HAS 5 LETTERS";
+        assert_eq!(
+            Event::parse(line).to_json(line),
+            r#"{"event":"word_length","letters":5,"line":"beef: THE WORD HAS 5 LETTERS"}"#
+        );
+        let line = "[whisper] 0007: \"hi\"\\";
+        assert_eq!(
+            Event::parse(line).to_json(line),
+            r#"{"event":"whisper","from":"0007","text":"\"hi\"\\","line":"[whisper] 0007: \"hi\"\\"}"#
+        );
+        assert_eq!(
+            Event::Squashed.to_json(""),
+            r#"{"event":"squashed","line":""}"#
+        );
+    }
+
+    #[test]
+    fn anything_else_is_kept_as_it_was() {
+        for line in [
+            "      ENTER l TO LIST OTHER USERS,",
+            "[nonsense] a03f: hi",
+            "beef: WELCOME, a03f!",
+        ] {
+            assert_eq!(Event::parse(line), Event::Line(line.to_string()));
+        }
+    }
+}
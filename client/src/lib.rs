@@ -0,0 +1,222 @@
+extern crate beef_messages;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::Duration;
+
+use beef_messages::BeefMessage;
+
+pub use crate::command::{Command, CommandError, HELP};
+pub use crate::error::{ClientError, ClientResult};
+pub use crate::event::Event;
+pub use crate::transport::{Address, Transport, DEFAULT_ADDRESS, DEFAULT_SOCKET};
+
+pub mod command;
+mod error;
+mod event;
+mod transport;
+
+pub type ClientId = u16;
+
+// what the server asked for after the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Auth {
+    // a new account, the password sent next will be its password
+    New,
+    // logging back in, the password has to match
+    Existing(ClientId),
+}
+
+// a connection to the server, with the protocol taken care of
+pub struct Client {
+    reader: BufReader<Box<dyn Transport>>,
+    writer: Box<dyn Transport>,
+    id: Option<ClientId>,
+}
+
+impl Client {
+    // just connects, nothing is sent until the handshake
+    pub fn connect(address: &Address) -> ClientResult<Self> {
+        let writer = address.connect()?;
+        Ok(Client {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+            id: None,
+        })
+    }
+
+    // connects and makes a new account
+    pub fn sign_up(address: &Address, password: &str) -> ClientResult<Self> {
+        let mut client = Client::connect(address)?;
+        client.handshake(None)?;
+        client.authenticate(password)?;
+        Ok(client)
+    }
+
+    // connects and logs back into an existing account
+    pub fn log_in(address: &Address, id: ClientId, password: &str) -> ClientResult<Self> {
+        let mut client = Client::connect(address)?;
+        client.handshake(Some(id))?;
+        client.authenticate(password)?;
+        Ok(client)
+    }
+
+    // connects again after losing the server. one that restarted has forgotten every account,
+    // which is NoAccount, trying again won't bring it back
+    pub fn resume(address: &Address, id: ClientId, password: &str) -> ClientResult<Self> {
+        match Client::log_in(address, id, password) {
+            Err(ClientError::Refused(line)) if line.contains("NO ACCOUNT") => {
+                Err(ClientError::NoAccount(id))
+            }
+            result => result,
+        }
+    }
+
+    // says hello, and reads until the server asks for a password. the password can't be sent
+    // along with the handshake, the server drops anything that comes with it
+    pub fn handshake(&mut self, id: Option<ClientId>) -> ClientResult<Auth> {
+        let handshake = match id {
+            Some(id) => format!("beef {id:x}\r\n\r\n"),
+            None => "beef\r\n\r\n".to_string(),
+        };
+        self.writer.write_all(handshake.as_bytes())?;
+        let line = self.expect_line()?;
+        if line != "beef: PROTOCOL ENGAGED" {
+            return Err(refused_or_unexpected(line));
+        }
+        let line = self.expect_line()?;
+        match id {
+            None if line.starts_with("beef: AUTH NEW") => Ok(Auth::New),
+            Some(id) if line.starts_with("beef: AUTH EXISTING") => Ok(Auth::Existing(id)),
+            _ => Err(refused_or_unexpected(line)),
+        }
+    }
+
+    // sends the password once the handshake asked for it, and finds out who we are
+    pub fn authenticate(&mut self, password: &str) -> ClientResult<ClientId> {
+        self.send(BeefMessage::Message(password.as_bytes().to_vec()))?;
+        let line = self.expect_line()?;
+        // the rest of the welcome, the list of commands, is left to whoever reads next
+        let id = line
+            .strip_prefix("beef: WELCOME, ")
+            .and_then(|rest| rest.strip_suffix('!'))
+            .or_else(|| line.strip_prefix("beef: AUTH SUCCESS "))
+            .and_then(|id| ClientId::from_str_radix(id, 16).ok());
+        let Some(id) = id else {
+            return Err(refused_or_unexpected(line));
+        };
+        self.id = Some(id);
+        Ok(id)
+    }
+
+    // the id we're signed in with, once authenticated
+    pub fn id(&self) -> Option<ClientId> {
+        self.id
+    }
+
+    // another handle to the same connection, e.g. to read on one thread and send on another.
+    // lines already read ahead stay with the original, so that's the one to keep reading from
+    pub fn try_clone(&self) -> ClientResult<Self> {
+        Ok(Client {
+            reader: BufReader::new(self.writer.try_clone()?),
+            writer: self.writer.try_clone()?,
+            id: self.id,
+        })
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> ClientResult<()> {
+        Ok(self.writer.set_read_timeout(timeout)?)
+    }
+
+    pub fn send(&mut self, msg: BeefMessage) -> ClientResult<()> {
+        let bytes: Vec<u8> = msg.into();
+        Ok(self.writer.write_all(&bytes)?)
+    }
+
+    pub fn list(&mut self) -> ClientResult<()> {
+        self.send(BeefMessage::List)
+    }
+
+    pub fn challenge(&mut self, to: ClientId, word: &str) -> ClientResult<()> {
+        self.send(BeefMessage::BattleInit(to, word.as_bytes().to_vec()))
+    }
+
+    pub fn guess(&mut self, word: &str) -> ClientResult<()> {
+        self.send(BeefMessage::BattleGuess(word.as_bytes().to_vec()))
+    }
+
+    pub fn forfeit(&mut self) -> ClientResult<()> {
+        self.send(BeefMessage::BattleForfeit)
+    }
+
+    // talks to whoever we're beefing with
+    pub fn chat(&mut self, text: &str) -> ClientResult<()> {
+        self.send(BeefMessage::Message(text.as_bytes().to_vec()))
+    }
+
+    // talks to everyone online
+    pub fn lobby(&mut self, text: &str) -> ClientResult<()> {
+        self.send(BeefMessage::Lobby(text.as_bytes().to_vec()))
+    }
+
+    pub fn whisper(&mut self, to: ClientId, text: &str) -> ClientResult<()> {
+        self.send(BeefMessage::Whisper(to, text.as_bytes().to_vec()))
+    }
+
+    // says goodbye and hangs up
+    pub fn disconnect(mut self) -> ClientResult<()> {
+        let result = self.send(BeefMessage::Disconnect);
+        let _ = self.writer.shutdown();
+        result
+    }
+
+    // the next line from the server, None once it hung up. pings are answered on the way
+    pub fn next_line(&mut self) -> ClientResult<Option<String>> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            // replies sometimes end in an empty line, there's nothing in those
+            if line.is_empty() {
+                continue;
+            }
+            if line == "beef: PING" {
+                self.send(BeefMessage::Pong)?;
+                continue;
+            }
+            return Ok(Some(line.to_string()));
+        }
+    }
+
+    pub fn next_event(&mut self) -> ClientResult<Option<Event>> {
+        Ok(self.next_line()?.map(|line| Event::parse(&line)))
+    }
+
+    // every line the server sends, until it hangs up
+    pub fn lines(&mut self) -> impl Iterator<Item = ClientResult<String>> + '_ {
+        std::iter::from_fn(move || self.next_line().transpose())
+    }
+
+    // every line the server sends as an event, until it hangs up
+    pub fn events(&mut self) -> impl Iterator<Item = ClientResult<Event>> + '_ {
+        std::iter::from_fn(move || self.next_event().transpose())
+    }
+
+    fn expect_line(&mut self) -> ClientResult<String> {
+        self.next_line()?.ok_or_else(|| {
+            ClientError::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "server hung up",
+            ))
+        })
+    }
+}
+
+fn refused_or_unexpected(line: String) -> ClientError {
+    if line.starts_with("beef: AUTH FAILURE") || line.starts_with("beef: SERVER FULL") {
+        ClientError::Refused(line)
+    } else {
+        ClientError::Unexpected(line)
+    }
+}
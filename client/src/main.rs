@@ -1,88 +1,278 @@
-use std::io::{Read, Write};
-use std::net::TcpStream;
-use std::{io, thread};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::{process, thread};
 
 use beef_messages::BeefMessage;
+use wguessr_client::command::complete;
+use wguessr_client::{
+    Address, Auth, Client, ClientError, ClientId, ClientResult, Command, Event, HELP,
+};
 
-fn main() -> std::io::Result<()> {
-    // perform initial handshake, sending 'beef\r\n\r\n'
-    let mut write_stream = TcpStream::connect("127.0.0.1:1234")?;
-    write_stream.write_all("beef\r\n\r\n".as_ref()).unwrap();
+use crate::editor::Terminal;
+use crate::reconnect::reconnect;
 
-    // pipe all from tcp stream to stdout
-    let mut read_stream = write_stream.try_clone().unwrap();
-    thread::spawn(move || loop {
-        let mut buffer = [0; 16];
-        match read_stream.read(&mut buffer) {
-            Ok(_) => {
-                io::stdout().write_all(&buffer).unwrap();
-            }
-            Err(_) => {
-                break;
+mod editor;
+mod reconnect;
+mod script;
+mod tui;
+
+const USAGE: &str = "usage: wguessr-client [--tui | --script <file|-> --password <password>]
+                      [--tcp <host:port> | --unix <path> | <beef url>] [<id>]";
+
+// what the client was started with
+#[derive(Default)]
+struct Options {
+    address: Address,
+    // logs back into this account instead of making a new one
+    id: Option<ClientId>,
+    full_screen: bool,
+    password: Option<String>,
+    // runs this instead of taking commands from the keyboard, - for stdin
+    script: Option<String>,
+}
+
+fn main() {
+    let options = parse_options(std::env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        eprintln!("{USAGE}");
+        process::exit(2);
+    });
+    if let Some(path) = options.script.clone() {
+        process::exit(run_script(options, &path));
+    }
+    if let Err(error) = run(options) {
+        eprintln!("{error}");
+        process::exit(1);
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--tui" => options.full_screen = true,
+            "--tcp" => options.address = Address::Tcp(value()?),
+            "--unix" => options.address = Address::Unix(PathBuf::from(value()?)),
+            "--password" => options.password = Some(value()?),
+            "--script" => options.script = Some(value()?),
+            "--help" | "-h" => return Err("wguessr-client, plays beef on a wguessr server".into()),
+            _ if arg.contains("://") => options.address = arg.parse()?,
+            _ if options.id.is_none() && !arg.starts_with('-') => {
+                let id = ClientId::from_str_radix(&arg, 16);
+                options.id = Some(id.map_err(|_| format!("{arg} is not an id"))?);
             }
+            _ => return Err(format!("don't know what to do with {arg}")),
         }
-    });
+    }
+    // nobody is there to type it in
+    if options.script.is_some() && options.password.is_none() {
+        return Err("--script needs a --password".to_string());
+    }
+    if options.script.is_some() && options.full_screen {
+        return Err("--script and --tui don't go together".to_string());
+    }
+    Ok(options)
+}
 
-    // pipe all from stdin to tcp stream
-    loop {
-        let mut buffer: Vec<_> = io::stdin()
-            .bytes()
-            .map(|x| x.unwrap())
-            .take_while(|x| *x != 10)
-            .collect();
-
-        // parse literal  into two bytes (e.g. a03f -> xa0 x3f) for ClientId
-        let first = match buffer.first() {
-            None => {
-                continue;
+// the password typed in is kept in the options, for logging back in after losing the server
+fn sign_in(options: &mut Options) -> ClientResult<(Client, ClientId)> {
+    let mut client = Client::connect(&options.address).inspect_err(|_| {
+        eprintln!("can't reach the server at {}", options.address);
+    })?;
+    let auth = client.handshake(options.id)?;
+    if options.password.is_none() {
+        match auth {
+            Auth::New => print!("password for your new account: "),
+            Auth::Existing(id) => print!("password for {id:x}: "),
+        }
+        io::stdout().flush()?;
+        let mut password = String::new();
+        io::stdin().read_line(&mut password)?;
+        options.password = Some(password.trim_end().to_string());
+    }
+    let id = client.authenticate(options.password.as_deref().unwrap_or_default())?;
+    Ok((client, id))
+}
+
+// what the exit code will be
+fn run_script(mut options: Options, path: &str) -> i32 {
+    let script: Box<dyn BufRead> = match path {
+        "-" => Box::new(io::stdin().lock()),
+        path => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(error) => {
+                println!("{}", script::failure(0, path, &error.to_string()));
+                return script::BROKEN;
             }
-            Some(x) => x,
-        };
-        if b"b"[0].eq(first) && buffer.len() >= 5 {
-            let [a, b, x, y] = &buffer[1..5] else {
-                continue;
-            };
-            buffer.splice(1..5, parse_literal_into_byte(&[*a, *b, *x, *y]));
+        },
+    };
+    match sign_in(&mut options) {
+        Ok((client, id)) => {
+            println!("{{\"event\":\"signed_in\",\"id\":\"{id:04x}\"}}");
+            script::run(client, script)
         }
+        Err(error) => {
+            println!("{}", script::failure(0, "sign in", &error.to_string()));
+            script::FAILED
+        }
+    }
+}
 
-        match send_beef(&mut write_stream, buffer.into()) {
-            Ok(BeefMessage::Disconnect) => {
+// the connection typed commands go out on, None while the reader is getting a new one or once
+// quitting took it
+type Connection = Arc<Mutex<Option<Client>>>;
+
+fn run(mut options: Options) -> ClientResult<()> {
+    let (mut client, id) = sign_in(&mut options)?;
+    println!("signed in as {id:x}, /help lists the commands");
+
+    let terminal = Terminal::new(id, options.full_screen);
+    // everyone we've heard of, for tab to complete
+    let users = Arc::new(Mutex::new(BTreeSet::new()));
+
+    // the original keeps reading, whatever it already buffered would be lost on a clone
+    let connection: Connection = Arc::new(Mutex::new(Some(client.try_clone()?)));
+    let reader_terminal = terminal.clone();
+    let reader_users = Arc::clone(&users);
+    let reader_connection = Arc::clone(&connection);
+    let password = options.password.unwrap_or_default();
+    thread::spawn(move || loop {
+        for line in client.lines() {
+            let Ok(line) = line else {
                 break;
+            };
+            remember_users(&mut reader_users.lock().unwrap(), &line);
+            reader_terminal.print(&line);
+        }
+        // nothing was taken when quitting did it first
+        if reader_connection.lock().unwrap().take().is_none() {
+            return;
+        }
+        let (again, writer) = match reconnect(&options.address, id, &password, &reader_terminal) {
+            Ok(clients) => clients,
+            // a new account would be somebody else to everyone, that's for the user to decide
+            Err(ClientError::NoAccount(_)) => {
+                reader_terminal.restore();
+                eprintln!(
+                    "the server at {} was restarted and forgot {id:x}, run the client without an \
+                     id to sign up again",
+                    options.address
+                );
+                process::exit(1);
             }
-            Ok(_) => {}
             Err(_) => {
-                break;
+                reader_terminal.restore();
+                eprintln!("gave up on the server at {}", options.address);
+                process::exit(1);
+            }
+        };
+        reader_terminal.note(&format!("back in as {id:x}"));
+        reader_users.lock().unwrap().clear();
+        reader_terminal.reconnected(id);
+        *reader_connection.lock().unwrap() = Some(writer);
+        client = again;
+    });
+
+    let typing = type_commands(&terminal, &users, &connection);
+    terminal.restore();
+    typing?;
+    let writer = connection.lock().unwrap().take();
+    writer.map_or(Ok(()), Client::disconnect)
+}
+
+// sends whatever is typed until told to quit
+fn type_commands(
+    terminal: &Terminal,
+    users: &Mutex<BTreeSet<ClientId>>,
+    connection: &Mutex<Option<Client>>,
+) -> ClientResult<()> {
+    loop {
+        let typed = terminal.read_line(|typed| {
+            let me = connection.lock().unwrap().as_ref().and_then(Client::id);
+            let users = users.lock().unwrap();
+            // nobody beefs with themselves
+            let others = users.iter().copied().filter(|user| Some(*user) != me);
+            complete(typed, others)
+        });
+        // ctrl-c, ctrl-d or the end of piped input
+        let Some(typed) = typed? else {
+            return Ok(());
+        };
+        if typed.trim().is_empty() {
+            continue;
+        }
+        match Command::parse(&typed).map(|command| command.to_message()) {
+            Err(error) => terminal.note(&error.to_string()),
+            Ok(None) => terminal.note(HELP),
+            Ok(Some(BeefMessage::Disconnect)) => return Ok(()),
+            Ok(Some(msg)) => {
+                terminal.sent(&msg);
+                // the reader notices a dead connection and gets a new one, until then nothing
+                // typed goes anywhere
+                let sent = match connection.lock().unwrap().as_mut() {
+                    Some(writer) => writer.send(msg).is_ok(),
+                    None => false,
+                };
+                if !sent {
+                    terminal.note("not connected, that wasn't sent");
+                }
             }
         }
     }
-
-    Ok(())
 }
 
-fn send_beef(stream: &mut TcpStream, msg: BeefMessage) -> std::io::Result<BeefMessage> {
-    let msg_raw: Vec<u8> = msg.clone().into();
-    stream.try_clone()?.write_all(msg_raw.as_slice())?;
-    Ok(msg)
+fn remember_users(users: &mut BTreeSet<ClientId>, line: &str) {
+    match Event::parse(line) {
+        Event::Listed { id, .. }
+        | Event::Challenged(id)
+        | Event::BattleStarted(id)
+        | Event::Chat { from: id, .. }
+        | Event::Lobby { from: id, .. }
+        | Event::Whisper { from: id, .. } => {
+            users.insert(id);
+        }
+        Event::OpponentLeft(id) => {
+            users.remove(&id);
+        }
+        // the list that follows is everyone, whoever isn't on it left
+        Event::UsersOnline => users.clear(),
+        _ => {}
+    }
 }
 
-pub fn parse_literal_into_byte(literal: &[u8; 4]) -> [u8; 2] {
-    let mut result = [0; 2];
-    for i in 0..2 {
-        let first_in_pair = match literal[i * 2] {
-            b @ b'0'..=b'9' => (b - b'0') << 4,
-            b @ b'a'..=b'f' => (b - b'a' + 10) << 4,
-            b @ b'A'..=b'F' => (b - b'A' + 10) << 4,
-            _ => b'0' << 4,
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // bitwise or with second byte
-        result[i] = first_in_pair
-            | match literal[i * 2 + 1] {
-                b @ b'0'..=b'9' => b - b'0',
-                b @ b'a'..=b'f' => b - b'a' + 10,
-                b @ b'A'..=b'F' => b - b'A' + 10,
-                _ => b'0',
-            };
+    fn options(args: &[&str]) -> Result<Options, String> {
+        parse_options(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn transport_comes_from_options_or_a_url() {
+        let unix = Address::Unix(PathBuf::from("/tmp/x.socket"));
+        assert_eq!(options(&[]).unwrap().address, Address::default());
+        assert_eq!(options(&["--unix", "/tmp/x.socket"]).unwrap().address, unix);
+        assert_eq!(
+            options(&["beef+unix:///tmp/x.socket"]).unwrap().address,
+            unix
+        );
+        let tcp = options(&["--tcp", "10.0.0.2:1234", "a03f"]).unwrap();
+        assert_eq!(tcp.address, Address::Tcp("10.0.0.2:1234".to_string()));
+        assert_eq!(tcp.id, Some(0xa03f));
+    }
+
+    #[test]
+    fn bad_options_are_refused() {
+        assert!(options(&["--unix"]).is_err());
+        assert!(options(&["http://localhost:1234"]).is_err());
+        assert!(options(&["a03f", "b04f"]).is_err());
+        assert!(options(&["--color"]).is_err());
+        assert!(options(&["--script", "-"]).is_err());
+        assert!(options(&["--script", "-", "--password", "x", "--tui"]).is_err());
     }
-    result
 }
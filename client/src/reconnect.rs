@@ -0,0 +1,80 @@
+use std::thread;
+use std::time::Duration;
+
+use wguessr_client::{Address, Client, ClientError, ClientId};
+
+use crate::editor::Terminal;
+
+// waits between tries start here and double every time
+const FIRST_WAIT: Duration = Duration::from_millis(500);
+const LONGEST_WAIT: Duration = Duration::from_secs(30);
+// a few minutes of trying all together, a server that isn't back by then won't be soon
+const TRIES: usize = 12;
+
+// how long to wait before every try
+struct Backoff {
+    wait: Duration,
+    left: usize,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Backoff {
+            wait: FIRST_WAIT,
+            left: TRIES,
+        }
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        self.left = self.left.checked_sub(1)?;
+        let wait = self.wait;
+        self.wait = (self.wait * 2).min(LONGEST_WAIT);
+        Some(wait)
+    }
+}
+
+// logs back in after the server went away, telling whoever is typing how it's going. comes back
+// with a client to read from and one to write to, or the last error once it gave up
+pub fn reconnect(
+    address: &Address,
+    id: ClientId,
+    password: &str,
+    terminal: &Terminal,
+) -> Result<(Client, Client), ClientError> {
+    terminal.note(&format!("lost the server at {address}, reconnecting"));
+    let mut backoff = Backoff::new().peekable();
+    loop {
+        thread::sleep(backoff.next().unwrap_or_default());
+        let resumed = Client::resume(address, id, password)
+            .and_then(|client| Ok((client.try_clone()?, client)));
+        let error = match resumed {
+            Ok((writer, reader)) => return Ok((reader, writer)),
+            Err(error @ ClientError::NoAccount(_)) => return Err(error),
+            Err(error) => error,
+        };
+        let Some(next) = backoff.peek() else {
+            return Err(error);
+        };
+        terminal.note(&format!(
+            "still no server ({error}), trying again in {}s",
+            next.as_secs_f64()
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_double_up_to_the_longest() {
+        let waits: Vec<_> = Backoff::new().map(|wait| wait.as_millis()).collect();
+        assert_eq!(waits.len(), TRIES);
+        assert_eq!(waits[..4], [500, 1000, 2000, 4000]);
+        assert_eq!(waits[TRIES - 1], LONGEST_WAIT.as_millis());
+    }
+}
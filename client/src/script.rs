@@ -0,0 +1,230 @@
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use beef_messages::{escape_json, BeefMessage};
+use wguessr_client::{Client, Command, Event};
+
+// how long an expect waits unless the script says otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// the script ran out of lines, or was told to quit
+pub const PASSED: i32 = 0;
+// an expectation wasn't met, or the server went away
+pub const FAILED: i32 = 1;
+// the script itself is wrong
+pub const BROKEN: i32 = 2;
+
+// one line of a script
+#[derive(Debug, PartialEq)]
+enum Step {
+    // anything the client takes when typed, /guess crane or p alike
+    Send(BeefMessage),
+    // waits for an event of this kind, * for any, whose line has the text in it
+    Expect { kind: String, text: Option<String> },
+    Timeout(Duration),
+    Sleep(Duration),
+    Quit,
+}
+
+fn parse_step(line: &str) -> Result<Option<Step>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim();
+    let seconds = || {
+        rest.parse::<f64>()
+            .ok()
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map(Duration::from_secs_f64)
+            .ok_or_else(|| format!("{word} takes seconds, not {rest:?}"))
+    };
+    let step = match word {
+        "expect" if rest.is_empty() => return Err("expect needs an event, or *".to_string()),
+        "expect" => {
+            let (kind, text) = rest.split_once(' ').unwrap_or((rest, ""));
+            Step::Expect {
+                kind: kind.to_string(),
+                text: Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+            }
+        }
+        "timeout" => Step::Timeout(seconds()?),
+        "sleep" => Step::Sleep(seconds()?),
+        _ => match Command::parse(line).map_err(|error| error.to_string())? {
+            Command::Help => return Err("/help means nothing to a script".to_string()),
+            Command::Quit => Step::Quit,
+            command => Step::Send(command.to_message().unwrap_or(BeefMessage::NotBeef)),
+        },
+    };
+    Ok(Some(step))
+}
+
+fn matches(kind: &str, text: &Option<String>, event: &Event, line: &str) -> bool {
+    (kind == "*" || kind == event.kind())
+        && text
+            .as_ref()
+            .is_none_or(|text| line.contains(text.as_str()))
+}
+
+// runs the script against the server, printing every event as json as it comes in. what
+// comes back is the exit code
+pub fn run(mut client: Client, script: impl BufRead) -> i32 {
+    let mut writer = match client.try_clone() {
+        Ok(writer) => writer,
+        Err(error) => {
+            println!("{}", failure(0, "connect", &error.to_string()));
+            return FAILED;
+        }
+    };
+    let (sender, events) = mpsc::channel();
+    // the original keeps reading, whatever it already buffered would be lost on a clone
+    thread::spawn(move || {
+        for line in client.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let event = Event::parse(&line);
+            println!("{}", event.to_json(&line));
+            if sender.send((event, line)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let code = run_steps(&mut writer, script, &events);
+    let _ = writer.disconnect();
+    code
+}
+
+fn run_steps(writer: &mut Client, script: impl BufRead, events: &Receiver<(Event, String)>) -> i32 {
+    let mut timeout = DEFAULT_TIMEOUT;
+    for (number, line) in script.lines().enumerate() {
+        let number = number + 1;
+        let Ok(line) = line else {
+            println!("{}", failure(number, "read", "script isn't readable"));
+            return BROKEN;
+        };
+        let step = match parse_step(&line) {
+            Ok(Some(step)) => step,
+            Ok(None) => continue,
+            Err(reason) => {
+                println!("{}", failure(number, &line, &reason));
+                return BROKEN;
+            }
+        };
+        match step {
+            Step::Send(msg) => {
+                if let Err(error) = writer.send(msg) {
+                    println!("{}", failure(number, &line, &error.to_string()));
+                    return FAILED;
+                }
+            }
+            Step::Expect { kind, text } => {
+                if let Err(reason) = expect(events, &kind, &text, timeout) {
+                    println!("{}", failure(number, &line, &reason));
+                    return FAILED;
+                }
+            }
+            Step::Timeout(duration) => timeout = duration,
+            Step::Sleep(duration) => thread::sleep(duration),
+            Step::Quit => break,
+        }
+    }
+    PASSED
+}
+
+// skips events until one matches, everything received since the last expect counts
+fn expect(
+    events: &Receiver<(Event, String)>,
+    kind: &str,
+    text: &Option<String>,
+    timeout: Duration,
+) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match events.recv_timeout(left) {
+            Ok((event, line)) if matches(kind, text, &event, &line) => return Ok(()),
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(format!("nothing like it within {}s", timeout.as_secs_f64()))
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err("server hung up".to_string()),
+        }
+    }
+}
+
+pub fn failure(number: usize, step: &str, reason: &str) -> String {
+    format!(
+        "{{\"failed\":\"{}\",\"step\":{number},\"reason\":\"{}\"}}",
+        escape_json(step),
+        escape_json(reason)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_lines_become_steps() {
+        assert_eq!(parse_step("  # just a comment"), Ok(None));
+        assert_eq!(parse_step(""), Ok(None));
+        assert_eq!(
+            parse_step("/guess crane"),
+            Ok(Some(Step::Send(BeefMessage::BattleGuess(
+                b"crane".to_vec()
+            ))))
+        );
+        assert_eq!(parse_step("p"), Ok(Some(Step::Send(BeefMessage::Practice))));
+        assert_eq!(
+            parse_step("expect line USERS ONLINE:"),
+            Ok(Some(Step::Expect {
+                kind: "line".to_string(),
+                text: Some("USERS ONLINE:".to_string())
+            }))
+        );
+        assert_eq!(
+            parse_step("timeout 0.5"),
+            Ok(Some(Step::Timeout(Duration::from_millis(500))))
+        );
+        assert_eq!(parse_step("/quit"), Ok(Some(Step::Quit)));
+    }
+
+    #[test]
+    fn broken_lines_say_why() {
+        assert!(parse_step("expect").is_err());
+        assert!(parse_step("timeout soon").is_err());
+        assert!(parse_step("sleep -1").is_err());
+        assert!(parse_step("/beef zzzz apple").is_err());
+        assert!(parse_step("/help").is_err());
+    }
+
+    #[test]
+    fn expect_skips_until_a_match_or_gives_up() {
+        let (sender, events) = mpsc::channel();
+        for line in ["beef: PRACTICE STARTED!", "beef: THE WORD HAS 5 LETTERS"] {
+            sender.send((Event::parse(line), line.to_string())).unwrap();
+        }
+        let timeout = Duration::from_millis(10);
+        assert!(expect(&events, "word_length", &Some("5".to_string()), timeout).is_ok());
+        // the practice line was skipped on the way, it's gone
+        assert!(expect(&events, "*", &Some("PRACTICE".to_string()), timeout).is_err());
+        drop(sender);
+        assert_eq!(
+            expect(&events, "*", &None, timeout),
+            Err("server hung up".to_string())
+        );
+    }
+
+    #[test]
+    fn failures_are_json_too() {
+        assert_eq!(
+            failure(3, "expect \"x\"", "nothing"),
+            r#"{"failed":"expect \"x\"","step":3,"reason":"nothing"}"#
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+// the address the server listens on unless told otherwise
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:1234";
+// and the socket it listens on next to it
+pub const DEFAULT_SOCKET: &str = "/tmp/guess_a_word.socket";
+
+// where the server can be reached, written as beef+tcp://host:port (or just beef://host:port) and
+// beef+unix:///path/to/socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl Default for Address {
+    fn default() -> Self {
+        Address::Tcp(DEFAULT_ADDRESS.to_string())
+    }
+}
+
+impl Address {
+    pub fn connect(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(match self {
+            Address::Tcp(addr) => Box::new(TcpStream::connect(addr)?),
+            Address::Unix(path) => Box::new(UnixStream::connect(path)?),
+        })
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Address::Tcp(addr) => write!(f, "beef+tcp://{addr}"),
+            Address::Unix(path) => write!(f, "beef+unix://{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| format!("{url} is not a beef url, like beef://127.0.0.1:1234"))?;
+        match scheme {
+            "beef" | "beef+tcp" if rest.contains(':') && !rest.contains('/') => {
+                Ok(Address::Tcp(rest.to_string()))
+            }
+            "beef" | "beef+tcp" => Err(format!("{url} needs a host and a port")),
+            "beef+unix" if rest.starts_with('/') => Ok(Address::Unix(PathBuf::from(rest))),
+            "beef+unix" => Err(format!(
+                "{url} needs an absolute path, like beef+unix:///tmp/x"
+            )),
+            _ => Err(format!("{scheme} is not beef+tcp or beef+unix")),
+        }
+    }
+}
+
+// same idea as the server's transport, just the bits a client needs
+pub trait Transport: Read + Write + Send {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn shutdown(&self) -> io::Result<()>;
+}
+
+impl Transport for TcpStream {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(TcpStream::try_clone(self)?))
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+    fn shutdown(&self) -> io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+impl Transport for UnixStream {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(UnixStream::try_clone(self)?))
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+    fn shutdown(&self) -> io::Result<()> {
+        UnixStream::shutdown(self, Shutdown::Both)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_name_the_transport() {
+        assert_eq!(
+            "beef://127.0.0.1:1234".parse(),
+            Ok(Address::Tcp("127.0.0.1:1234".to_string()))
+        );
+        assert_eq!(
+            "beef+tcp://example.com:1234".parse(),
+            Ok(Address::Tcp("example.com:1234".to_string()))
+        );
+        assert_eq!(
+            "beef+unix:///tmp/guess_a_word.socket".parse(),
+            Ok(Address::Unix(PathBuf::from(DEFAULT_SOCKET)))
+        );
+        for address in [Address::default(), Address::Unix(PathBuf::from("/tmp/x"))] {
+            assert_eq!(address.to_string().parse(), Ok(address));
+        }
+    }
+
+    #[test]
+    fn bad_urls_say_why() {
+        for url in [
+            "127.0.0.1:1234",
+            "beef://localhost",
+            "beef+unix://tmp/x",
+            "http://localhost:1234",
+        ] {
+            assert!(url.parse::<Address>().is_err(), "{url}");
+        }
+    }
+}
@@ -0,0 +1,549 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+
+use beef_messages::{hint, BeefMessage};
+use wguessr_client::{ClientId, Event};
+
+// anything older is scrolled away for good
+const MAX_MESSAGES: usize = 500;
+const USERS_WIDTH: usize = 28;
+
+const RESET: &str = "\x1b[0m";
+const REVERSE: &str = "\x1b[7m";
+const DIM: &str = "\x1b[2m";
+const IN_PLACE: &str = "\x1b[30;42m";
+const ELSEWHERE: &str = "\x1b[30;43m";
+const NOT_IN_WORD: &str = "\x1b[37;100m";
+
+// everything the full screen shows, kept up to date from the server's lines
+pub struct Panes {
+    me: ClientId,
+    users: BTreeMap<ClientId, String>,
+    // a user list is coming in, line by line
+    listing: bool,
+    battle: Option<Battle>,
+    // what players say, beef chat, lobby and whispers
+    chat: VecDeque<String>,
+    // everything else the server says, and the client's own notes
+    status: VecDeque<String>,
+    // what we sent last, the server's answers don't repeat it
+    guessed: Option<String>,
+    picked: Option<String>,
+}
+
+#[derive(Default)]
+struct Battle {
+    // nobody for practice and the daily
+    opponent: Option<ClientId>,
+    is_guessing: bool,
+    letters: Option<usize>,
+    // only known when we picked it
+    target: Option<String>,
+    guesses: Vec<(String, Option<String>)>,
+    left: Option<usize>,
+    result: Option<String>,
+}
+
+impl Panes {
+    pub fn new(me: ClientId) -> Self {
+        Panes {
+            me,
+            users: BTreeMap::new(),
+            listing: false,
+            battle: None,
+            chat: VecDeque::new(),
+            status: VecDeque::new(),
+            guessed: None,
+            picked: None,
+        }
+    }
+
+    // keeps what's needed to make sense of the replies to it
+    pub fn sent(&mut self, msg: &BeefMessage) {
+        match msg {
+            BeefMessage::BattleGuess(word) => {
+                self.guessed = Some(String::from_utf8_lossy(word).trim().to_lowercase())
+            }
+            BeefMessage::BattleInit(_, word) | BeefMessage::Rematch(word) if !word.is_empty() => {
+                self.picked = Some(String::from_utf8_lossy(word).trim().to_lowercase())
+            }
+            _ => {}
+        }
+    }
+
+    pub fn update(&mut self, line: &str) {
+        let event = Event::parse(line);
+        if !matches!(event, Event::Listed { .. }) {
+            self.listing = false;
+        }
+        match event {
+            Event::UsersOnline => {
+                self.users.clear();
+                self.listing = true;
+                return;
+            }
+            Event::Listed { id, details } if self.listing => {
+                self.users.insert(id, details);
+                return;
+            }
+            Event::Challenged(id) => {
+                self.battle = Some(Battle {
+                    opponent: Some(id),
+                    is_guessing: true,
+                    ..Battle::default()
+                });
+            }
+            Event::BattleStarted(id) => {
+                self.battle = Some(Battle {
+                    opponent: Some(id),
+                    target: self.picked.take(),
+                    ..Battle::default()
+                });
+            }
+            Event::WordLength(letters) => {
+                let battle = match self.battle.take() {
+                    Some(battle) if battle.is_guessing && battle.result.is_none() => battle,
+                    _ => Battle {
+                        is_guessing: true,
+                        ..Battle::default()
+                    },
+                };
+                self.battle = Some(Battle {
+                    letters: Some(letters),
+                    ..battle
+                });
+                return;
+            }
+            Event::GuessesLeft(left) => {
+                if let Some(battle) = self.battle.as_mut() {
+                    battle.left = Some(left);
+                }
+                return;
+            }
+            Event::WrongGuess(hint) => {
+                let guess = self
+                    .guessed
+                    .take()
+                    .unwrap_or_else(|| "?".repeat(hint.len()));
+                if let Some(battle) = self.battle.as_mut() {
+                    battle.guesses.push((guess, Some(hint)));
+                }
+                return;
+            }
+            Event::OpponentGuessed(guess) => {
+                if let Some(battle) = self.battle.as_mut() {
+                    let hint = battle.target.as_ref().map(|target| hint(target, &guess));
+                    battle.guesses.push((guess, hint));
+                }
+                return;
+            }
+            Event::Squashed => {
+                // whoever guessed it last guessed the word, whether that was us or them
+                if let Some(battle) = self.battle.as_mut() {
+                    let word = match battle.is_guessing {
+                        true => self.guessed.take(),
+                        false => battle.target.clone(),
+                    };
+                    if let Some(word) = word {
+                        battle
+                            .guesses
+                            .push((word.clone(), Some(word.to_uppercase())));
+                    }
+                }
+                self.end_battle(line);
+            }
+            Event::Forfeited | Event::OpponentForfeited | Event::OpponentLeft(_) => {
+                self.end_battle(line)
+            }
+            Event::Line(ref text) if starts_battle(text) => {
+                // tournament matches are races against whoever they're paired with
+                let opponent = text.split_once(": BEEF WITH USER ").and_then(|(_, id)| {
+                    ClientId::from_str_radix(id.trim_end_matches('!'), 16).ok()
+                });
+                self.battle = Some(Battle {
+                    opponent,
+                    is_guessing: true,
+                    ..Battle::default()
+                });
+            }
+            Event::Line(ref text) if text.starts_with("beef: EARLIER GUESS ") => {
+                let earlier = text["beef: EARLIER GUESS ".len()..].split_once(' ');
+                if let (Some(battle), Some((guess, hint))) = (self.battle.as_mut(), earlier) {
+                    battle
+                        .guesses
+                        .push((guess.to_string(), Some(hint.to_string())));
+                }
+                return;
+            }
+            Event::Line(ref text) if ends_battle(text) => self.end_battle(line),
+            Event::Chat { .. } | Event::Lobby { .. } | Event::Whisper { .. } => {
+                push_lines(&mut self.chat, line);
+                return;
+            }
+            _ => {}
+        }
+        self.message(line);
+    }
+
+    // a new connection, whatever beef there was ended with the old one
+    pub fn reconnected(&mut self, me: ClientId) {
+        if let Some(battle) = self
+            .battle
+            .as_mut()
+            .filter(|battle| battle.result.is_none())
+        {
+            battle.result = Some("CONNECTION LOST".to_string());
+        }
+        self.me = me;
+        self.users.clear();
+        self.listing = false;
+    }
+
+    // for lines that aren't from the server, like help or errors
+    pub fn message(&mut self, line: &str) {
+        push_lines(&mut self.status, line);
+    }
+
+    fn end_battle(&mut self, line: &str) {
+        if let Some(battle) = self.battle.as_mut() {
+            battle.result = Some(line.trim_start_matches("beef: ").to_string());
+        }
+    }
+
+    // the whole screen, with what's being typed on the last line
+    pub fn render(&self, rows: usize, cols: usize, typed: &str) -> String {
+        let users_width = USERS_WIDTH.min(cols / 3);
+        let right_width = cols.saturating_sub(users_width + 1);
+        let body = rows.saturating_sub(2);
+        let battle_rows = body / 2;
+        // a line under each pane, and a third of what's left for the status area
+        let below = body.saturating_sub(battle_rows + 2);
+        let status_rows = below / 3;
+        let chat_rows = below - status_rows;
+
+        let users = self.users_pane();
+        let mut right = self.battle_pane(right_width, battle_rows);
+        right.resize(battle_rows, String::new());
+        right.push("─".repeat(right_width));
+        let chat = match self.chat.is_empty() {
+            true => vec![format!("{DIM}no chat yet, /say <text> to talk{RESET}")],
+            false => last_lines(&self.chat, chat_rows, right_width),
+        };
+        right.extend(chat);
+        right.resize(battle_rows + 1 + chat_rows, String::new());
+        right.push("─".repeat(right_width));
+        right.extend(last_lines(&self.status, status_rows, right_width));
+
+        let mut screen = String::new();
+        let title = format!(" wguessr, signed in as {:04x}", self.me);
+        let _ = write!(screen, "\x1b[1;1H{REVERSE}{}{RESET}", fit(&title, cols));
+        for row in 0..body {
+            let left = users
+                .get(row)
+                .map_or(String::new(), |line| fit(line, users_width));
+            let right = right.get(row).map_or("", String::as_str);
+            let _ = write!(
+                screen,
+                "\x1b[{};1H{}│{right}\x1b[K",
+                row + 2,
+                pad(&left, users_width)
+            );
+        }
+        let _ = write!(screen, "\x1b[{rows};1H> {typed}\x1b[K");
+        screen
+    }
+
+    fn users_pane(&self) -> Vec<String> {
+        let mut lines = vec![format!("{DIM}users, /list to refresh{RESET}")];
+        for (id, details) in &self.users {
+            let you = if *id == self.me { " (you)" } else { "" };
+            lines.push(format!("{id:04x}{you} {details}"));
+        }
+        lines
+    }
+
+    fn battle_pane(&self, width: usize, rows: usize) -> Vec<String> {
+        let Some(battle) = &self.battle else {
+            return vec![format!(
+                "{DIM}no beef going on, /beef <user> <word> or p to practice{RESET}"
+            )];
+        };
+        let with = match battle.opponent {
+            Some(id) => format!("beef with {id:04x}"),
+            None => "on your own".to_string(),
+        };
+        let mut lines = match (&battle.target, battle.is_guessing) {
+            (_, true) => vec![format!("{with}, you're guessing")],
+            (Some(target), false) => vec![format!("{with}, they're guessing {target}")],
+            (None, false) => vec![format!("{with}, they're guessing")],
+        };
+        let mut about = Vec::new();
+        if let Some(letters) = battle.letters {
+            about.push(format!("{letters} letters"));
+        }
+        if let Some(left) = battle.left {
+            about.push(format!("{left} guesses left"));
+        }
+        if !about.is_empty() {
+            lines.push(about.join(", "));
+        }
+        for (guess, hint) in &battle.guesses {
+            lines.push(match hint {
+                Some(hint) if guess.chars().count() * 3 <= width => colored(guess, hint),
+                _ => guess.clone(),
+            });
+        }
+        if let Some(result) = &battle.result {
+            lines.push(result.clone());
+        }
+        // the latest guesses matter most when they don't all fit, what the beef is about stays
+        let header = lines.len() - battle.guesses.len() - usize::from(battle.result.is_some());
+        if lines.len() > rows {
+            let drop = (lines.len() - rows).min(lines.len() - header);
+            lines.drain(header..header + drop);
+        }
+        lines.truncate(rows);
+        lines.into_iter().map(|line| fit(&line, width)).collect()
+    }
+}
+
+fn push_lines(pane: &mut VecDeque<String>, line: &str) {
+    for line in line.lines() {
+        pane.push_back(line.to_string());
+    }
+    while pane.len() > MAX_MESSAGES {
+        pane.pop_front();
+    }
+}
+
+// the newest lines that fit, cut to the pane's width
+fn last_lines(pane: &VecDeque<String>, rows: usize, width: usize) -> Vec<String> {
+    let skip = pane.len().saturating_sub(rows);
+    pane.iter()
+        .skip(skip)
+        .map(|line| fit(line, width))
+        .collect()
+}
+
+// practice, the daily and tournament matches don't start with a challenge
+fn starts_battle(line: &str) -> bool {
+    line.starts_with("beef: PRACTICE STARTED")
+        || (line.starts_with("beef: DAILY ") && line.ends_with(" STARTED!"))
+        || (line.starts_with("beef: TOURNAMENT #") && line.contains(": BEEF WITH USER "))
+}
+
+fn ends_battle(line: &str) -> bool {
+    [
+        "beef: OUT OF GUESSES",
+        "beef: GAVE UP",
+        "beef: OPPONENT IS OUT OF GUESSES",
+        "beef: OPPONENT GUESSED ",
+        "beef: SERVER SHUTTING DOWN, BEEF CALLED OFF",
+    ]
+    .iter()
+    .any(|start| line.starts_with(start))
+        && line != "beef: OPPONENT GUESSED WRONG"
+}
+
+// every letter of the guess on the color its hint gives it, like wordle does
+fn colored(guess: &str, hint: &str) -> String {
+    let mut line = String::new();
+    for (letter, mark) in guess
+        .chars()
+        .zip(hint.chars().chain(std::iter::repeat('_')))
+    {
+        let color = match mark {
+            '_' => NOT_IN_WORD,
+            mark if mark.is_uppercase() => IN_PLACE,
+            _ => ELSEWHERE,
+        };
+        let _ = write!(line, "{color} {} {RESET}", letter.to_uppercase());
+    }
+    line
+}
+
+// cut to the width of the pane, escapes don't take up any room
+fn fit(line: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut visible = 0;
+    let mut in_escape = false;
+    for c in line.chars() {
+        if c == '\x1b' {
+            in_escape = true;
+        }
+        if !in_escape {
+            if visible == width {
+                fitted.push_str(RESET);
+                break;
+            }
+            visible += 1;
+        }
+        fitted.push(c);
+        if in_escape && c.is_ascii_alphabetic() {
+            in_escape = false;
+        }
+    }
+    fitted
+}
+
+fn pad(line: &str, width: usize) -> String {
+    let mut visible = 0;
+    let mut in_escape = false;
+    for c in line.chars() {
+        match c {
+            '\x1b' => in_escape = true,
+            c if in_escape => in_escape = !c.is_ascii_alphabetic(),
+            _ => visible += 1,
+        }
+    }
+    format!("{line}{}", " ".repeat(width.saturating_sub(visible)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panes_after(lines: &[&str]) -> Panes {
+        let mut panes = Panes::new(0xa03f);
+        for line in lines {
+            panes.update(line);
+        }
+        panes
+    }
+
+    #[test]
+    fn user_list_replaces_the_users_pane() {
+        let panes = panes_after(&[
+            "beef: USERS ONLINE:",
+            "7b69 (bot, easy, available)",
+            "a03f (available)",
+            "beef: USERS ONLINE:",
+            "a03f (available)",
+            "beef: FRIENDS:",
+            "7b69 (offline)",
+        ]);
+        assert_eq!(panes.users.len(), 1);
+        assert_eq!(panes.status, ["beef: FRIENDS:", "7b69 (offline)"]);
+    }
+
+    #[test]
+    fn guesses_are_kept_with_their_hints() {
+        let mut panes = panes_after(&[
+            "beef: USER 7b69 HAS BEEF WITH YOU!",
+            "beef: WHAT IS YOUR RESPONSE?!",
+            "beef: THE WORD HAS 5 LETTERS",
+        ]);
+        panes.sent(&BeefMessage::BattleGuess(b"peach".to_vec()));
+        panes.update("beef: WRONG GUESS! _e_c_");
+        panes.sent(&BeefMessage::BattleGuess(b"crane".to_vec()));
+        panes.update("beef: GUESS CORRECT, BEEF SQUASHED!");
+
+        let battle = panes.battle.as_ref().unwrap();
+        assert_eq!(battle.opponent, Some(0x7b69));
+        assert_eq!(battle.letters, Some(5));
+        assert_eq!(
+            battle.guesses,
+            [
+                ("peach".to_string(), Some("_e_c_".to_string())),
+                ("crane".to_string(), Some("CRANE".to_string())),
+            ]
+        );
+        assert_eq!(
+            battle.result.as_deref(),
+            Some("GUESS CORRECT, BEEF SQUASHED!")
+        );
+    }
+
+    #[test]
+    fn picked_word_gives_hints_for_their_guesses() {
+        let mut panes = Panes::new(0xa03f);
+        panes.sent(&BeefMessage::BattleInit(0x7b69, b"crane".to_vec()));
+        panes.update("beef: STARTING BEEF WITH USER 7b69!");
+        panes.update("beef: WRONG GUESS cater");
+        let battle = panes.battle.as_ref().unwrap();
+        assert!(!battle.is_guessing);
+        assert_eq!(
+            battle.guesses,
+            [("cater".to_string(), Some("Ca_er".to_string()))]
+        );
+    }
+
+    #[test]
+    fn practice_starts_without_a_challenge() {
+        let panes = panes_after(&[
+            "beef: PRACTICE STARTED!",
+            "beef: THE WORD HAS 5 LETTERS",
+            "beef: 6 GUESSES LEFT",
+        ]);
+        let battle = panes.battle.as_ref().unwrap();
+        assert!(battle.is_guessing && battle.opponent.is_none());
+        assert_eq!(battle.left, Some(6));
+    }
+
+    #[test]
+    fn colors_follow_the_hint() {
+        assert_eq!(
+            colored("ca", "Ca"),
+            format!("{IN_PLACE} C {RESET}{ELSEWHERE} A {RESET}")
+        );
+        assert_eq!(colored("x", "_"), format!("{NOT_IN_WORD} X {RESET}"));
+    }
+
+    #[test]
+    fn fitting_ignores_escapes() {
+        assert_eq!(fit("abcdef", 3), format!("abc{RESET}"));
+        assert_eq!(
+            fit(&format!("{DIM}ab{RESET}"), 5),
+            format!("{DIM}ab{RESET}")
+        );
+        assert_eq!(
+            pad(&format!("{DIM}ab{RESET}"), 4),
+            format!("{DIM}ab{RESET}  ")
+        );
+    }
+
+    #[test]
+    fn chat_has_a_pane_of_its_own() {
+        let panes = panes_after(&[
+            "7b69: good luck",
+            "beef: CAN'T WHISPER, USER 1234 IS NOT ONLINE!",
+            "[whisper] 7b69: psst",
+            "[lobby] 7b69: hello",
+            "beef: PING",
+        ]);
+        assert_eq!(
+            panes.chat,
+            [
+                "7b69: good luck",
+                "[whisper] 7b69: psst",
+                "[lobby] 7b69: hello"
+            ]
+        );
+        assert_eq!(
+            panes.status,
+            [
+                "beef: CAN'T WHISPER, USER 1234 IS NOT ONLINE!",
+                "beef: PING"
+            ]
+        );
+    }
+
+    #[test]
+    fn screen_has_every_pane() {
+        let panes = panes_after(&[
+            "beef: USERS ONLINE:",
+            "7b69 (bot, easy, available)",
+            "[lobby] 7b69: hello",
+            "beef: STATUS SET TO AWAY",
+        ]);
+        let screen = panes.render(12, 80, "/gu");
+        assert!(screen.contains("signed in as a03f"));
+        assert!(screen.contains("7b69 bot, easy, available"));
+        assert!(screen.contains("no beef going on"));
+        assert!(screen.contains("[lobby] 7b69: hello"));
+        assert!(screen.contains("beef: STATUS SET TO AWAY"));
+        // chat comes before the status area
+        assert!(screen.find("hello") < screen.find("STATUS SET"));
+        assert!(screen.ends_with("\x1b[12;1H> /gu\x1b[K"));
+    }
+}
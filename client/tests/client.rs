@@ -0,0 +1,221 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use std::{env, fs, process};
+
+use wguessr_client::{Address, Auth, Client, ClientError, Event};
+use wguessr_server::config::Config;
+use wguessr_server::Server;
+
+static SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+// a real server for every test, reachable over both tcp and the unix socket
+struct TestServer {
+    server: Server,
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let number = SERVERS.fetch_add(1, Ordering::SeqCst);
+    env::temp_dir().join(format!("wguessr-client-{}-{number}.{name}", process::id()))
+}
+
+impl TestServer {
+    fn start() -> Self {
+        let path = temp_path("socket");
+        let server = Server::start(Config::default(), "127.0.0.1:0", path.to_str().unwrap())
+            .expect("server didn't start");
+        TestServer { server }
+    }
+
+    fn tcp(&self) -> Address {
+        Address::Tcp(self.server.tcp_addr.to_string())
+    }
+
+    fn unix(&self) -> Address {
+        Address::Unix(self.server.socket_path.clone())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.server.socket_path);
+    }
+}
+
+fn sign_up(address: &Address) -> Client {
+    let client = Client::sign_up(address, "password").unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    client
+}
+
+// the next event that isn't just a line of text
+fn next_event(client: &mut Client) -> Event {
+    client
+        .events()
+        .map(Result::unwrap)
+        .find(|event| !matches!(event, Event::Line(_)))
+        .expect("server hung up")
+}
+
+#[test]
+fn battle_over_tcp_and_unix() {
+    let server = TestServer::start();
+    let mut a = sign_up(&server.tcp());
+    let mut b = sign_up(&server.unix());
+    let (a_id, b_id) = (a.id().unwrap(), b.id().unwrap());
+
+    a.challenge(b_id, "apple").unwrap();
+    assert_eq!(next_event(&mut a), Event::BattleStarted(b_id));
+    assert_eq!(next_event(&mut b), Event::Challenged(a_id));
+    assert_eq!(next_event(&mut b), Event::WordLength(5));
+
+    b.chat("hm").unwrap();
+    assert_eq!(
+        next_event(&mut a),
+        Event::Chat {
+            from: b_id,
+            text: "hm".to_string()
+        }
+    );
+
+    b.guess("peach").unwrap();
+    assert!(matches!(next_event(&mut b), Event::WrongGuess(_)));
+    assert_eq!(
+        next_event(&mut a),
+        Event::OpponentGuessed("peach".to_string())
+    );
+
+    b.guess("apple").unwrap();
+    assert_eq!(next_event(&mut a), Event::Squashed);
+    assert_eq!(next_event(&mut b), Event::Squashed);
+
+    a.challenge(b_id, "pear").unwrap();
+    assert_eq!(next_event(&mut a), Event::BattleStarted(b_id));
+    assert_eq!(next_event(&mut b), Event::Challenged(a_id));
+    assert_eq!(next_event(&mut b), Event::WordLength(4));
+    b.forfeit().unwrap();
+    assert_eq!(next_event(&mut b), Event::Forfeited);
+    assert_eq!(next_event(&mut a), Event::OpponentForfeited);
+}
+
+#[test]
+fn logging_back_in() {
+    let server = TestServer::start();
+    let a = sign_up(&server.tcp());
+    let id = a.id().unwrap();
+
+    // still online, so the server says no before asking for a password
+    let mut again = Client::connect(&server.unix()).unwrap();
+    assert!(matches!(
+        again.handshake(Some(id)),
+        Err(ClientError::Refused(_))
+    ));
+
+    a.disconnect().unwrap();
+    let mut back = Client::connect(&server.unix()).unwrap();
+    assert_eq!(back.handshake(Some(id)).unwrap(), Auth::Existing(id));
+    assert_eq!(back.authenticate("password").unwrap(), id);
+
+    let mut wrong = Client::connect(&server.tcp()).unwrap();
+    assert!(wrong.handshake(Some(0xffff)).is_err());
+}
+
+#[test]
+fn resuming_after_losing_the_server() {
+    let server = TestServer::start();
+    let a = sign_up(&server.tcp());
+    let id = a.id().unwrap();
+    a.disconnect().unwrap();
+
+    let back = Client::resume(&server.unix(), id, "password").unwrap();
+    assert_eq!(back.id(), Some(id));
+    // a restarted server knows nobody, and nobody gets signed up behind the user's back
+    let forgotten = id.wrapping_add(1);
+    assert!(matches!(
+        Client::resume(&server.tcp(), forgotten, "password"),
+        Err(ClientError::NoAccount(id)) if id == forgotten
+    ));
+}
+
+#[test]
+fn resuming_after_the_server_restarted() {
+    let accounts = temp_path("accounts");
+    let socket = temp_path("socket");
+    let start = |accounts_file: Option<&PathBuf>| {
+        let config = Config {
+            shutdown_countdown: Duration::ZERO,
+            accounts_file: accounts_file.cloned(),
+            ..Config::default()
+        };
+        Server::start(config, "127.0.0.1:0", socket.to_str().unwrap()).unwrap()
+    };
+
+    // with an accounts file, the account is still there after a restart
+    let server = start(Some(&accounts));
+    let a = sign_up(&Address::Unix(socket.clone()));
+    let id = a.id().unwrap();
+    server.shut_down().unwrap();
+    drop(a);
+    let server = start(Some(&accounts));
+    let back = Client::resume(&Address::Unix(socket.clone()), id, "password").unwrap();
+    assert_eq!(back.id(), Some(id));
+    server.shut_down().unwrap();
+    drop(back);
+
+    // without one it's gone, and the client is told instead of getting a new account
+    let server = start(None);
+    assert!(matches!(
+        Client::resume(&Address::Unix(socket.clone()), id, "password"),
+        Err(ClientError::NoAccount(forgotten)) if forgotten == id
+    ));
+    server.shut_down().unwrap();
+    fs::remove_file(&accounts).unwrap();
+}
+
+// runs the client binary with a script on its stdin, for the exit code and what it printed
+fn run_script(server: &TestServer, script: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wguessr-client"))
+        .args(["--script", "-", "--password", "password"])
+        .arg(server.unix().to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn scripts_pass_or_fail() {
+    let server = TestServer::start();
+    let (code, out) = run_script(
+        &server,
+        "p\nexpect word_length\n/guess zzzzz\nexpect guesses_left 5\n/forfeit\nexpect line GAVE UP\n",
+    );
+    assert_eq!(code, 0, "{out}");
+    assert!(out.starts_with("{\"event\":\"signed_in\""));
+    assert!(out.contains("{\"event\":\"guesses_left\",\"left\":5,"));
+
+    let (code, out) = run_script(&server, "timeout 0.2\nexpect squashed\n");
+    assert_eq!(code, 1);
+    assert!(out.ends_with(
+        "{\"failed\":\"expect squashed\",\"step\":2,\"reason\":\"nothing like it within 0.2s\"}\n"
+    ));
+
+    let (code, _) = run_script(&server, "/beef nobody apple\n");
+    assert_eq!(code, 2);
+}
@@ -0,0 +1,226 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{env, process};
+
+use wguessr_client::{Address, Client, ClientError};
+
+use crate::player::Plan;
+use crate::stats::Stats;
+
+mod player;
+mod stats;
+
+const USAGE: &str = "usage: wguessr-loadgen [--connections <n>] [--rate <guesses per second>]
+                       [--duration <seconds>] [--guesses <wrong ones per beef>]
+                       [--tcp <host:port> | --unix <path> | <beef url>]";
+// every connection is a new account, they're all given this one
+const PASSWORD: &str = "loadgen";
+const SIGN_UP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// what the load looks like
+#[derive(Debug, PartialEq)]
+struct Options {
+    address: Address,
+    // two for every beef, one picks and one guesses
+    connections: usize,
+    // guesses per second by every guesser
+    rate: f64,
+    duration: Duration,
+    // wrong guesses before the right one, so beefs last a while
+    wrong_guesses: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            address: Address::default(),
+            connections: 20,
+            rate: 1.0,
+            duration: Duration::from_secs(10),
+            wrong_guesses: 5,
+        }
+    }
+}
+
+fn main() {
+    let options = parse_options(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        eprintln!("{USAGE}");
+        process::exit(2);
+    });
+    process::exit(run(options));
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        let mut number = || {
+            let value = value()?;
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite() && *number > 0.0)
+                .ok_or_else(|| format!("{arg} takes a number above 0, not {value}"))
+        };
+        match arg.as_str() {
+            "--connections" => {
+                let value = value()?;
+                options.connections = value
+                    .parse()
+                    .map_err(|_| format!("--connections takes a count, not {value}"))?;
+            }
+            "--rate" => options.rate = number()?,
+            "--duration" => options.duration = Duration::from_secs_f64(number()?),
+            "--guesses" => {
+                let value = value()?;
+                options.wrong_guesses = value
+                    .parse()
+                    .map_err(|_| format!("--guesses takes a count, not {value}"))?;
+            }
+            "--tcp" => options.address = Address::Tcp(value()?),
+            "--unix" => options.address = Address::Unix(PathBuf::from(value()?)),
+            "--help" | "-h" => {
+                return Err("wguessr-loadgen, plays a lot of beefs on a wguessr server".into())
+            }
+            _ if arg.contains("://") => options.address = arg.parse()?,
+            _ => return Err(format!("don't know what to do with {arg}")),
+        }
+    }
+    if options.connections < 2 || options.connections % 2 != 0 {
+        return Err("--connections has to be even, players come in pairs".to_string());
+    }
+    Ok(options)
+}
+
+// what the exit code will be, 1 when not a single beef could be played
+fn run(options: Options) -> i32 {
+    let stats = Arc::new(Mutex::new(Stats::default()));
+    println!(
+        "signing up {} players at {}",
+        options.connections, options.address
+    );
+    let signing_up = Instant::now();
+    // everyone at once, some servers only start to struggle then
+    let pairs: Vec<_> = (0..options.connections / 2)
+        .map(|_| {
+            let address = options.address.clone();
+            let stats = Arc::clone(&stats);
+            thread::spawn(move || Some((sign_up(&address, &stats)?, sign_up(&address, &stats)?)))
+        })
+        .collect();
+    let pairs: Vec<_> = pairs
+        .into_iter()
+        .filter_map(|pair| pair.join().ok().flatten())
+        .collect();
+    println!(
+        "{} pairs ready after {:.2}s, playing for {}s",
+        pairs.len(),
+        signing_up.elapsed().as_secs_f64(),
+        options.duration.as_secs_f64()
+    );
+    if pairs.is_empty() {
+        print!(
+            "{}",
+            stats
+                .lock()
+                .unwrap()
+                .report(options.connections, Duration::ZERO)
+        );
+        return 1;
+    }
+
+    let started = Instant::now();
+    let plan = Plan {
+        every: Duration::from_secs_f64(1.0 / options.rate),
+        wrong_guesses: options.wrong_guesses,
+        until: started + options.duration,
+    };
+    for (picker, guesser) in pairs {
+        player::play(picker, guesser, plan, &stats);
+    }
+    thread::sleep(options.duration);
+    // whoever is still waiting for an answer is left to it, leaving hangs up on them all
+    let stats = stats.lock().unwrap();
+    print!("{}", stats.report(options.connections, started.elapsed()));
+    0
+}
+
+fn sign_up(address: &Address, stats: &Mutex<Stats>) -> Option<Client> {
+    let signed_up = Client::connect(address).and_then(|mut client| {
+        // a full server can keep new connections waiting in line for as long as it likes
+        client.set_read_timeout(Some(SIGN_UP_TIMEOUT))?;
+        client.handshake(None)?;
+        client.authenticate(PASSWORD)?;
+        client.set_read_timeout(None)?;
+        Ok(client)
+    });
+    let mut stats = stats.lock().unwrap();
+    match signed_up {
+        Ok(client) => {
+            stats.signed_in += 1;
+            Some(client)
+        }
+        Err(ClientError::Refused(line)) => {
+            stats.error(line.strip_prefix("beef: ").unwrap_or(&line));
+            None
+        }
+        Err(ClientError::Io(error))
+            if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+        {
+            stats.error("no answer to signing up");
+            None
+        }
+        Err(error) => {
+            stats.error(&error.to_string());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        parse_options(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_shape_the_load() {
+        assert_eq!(options(&[]).unwrap(), Options::default());
+        let options = options(&[
+            "--connections",
+            "100",
+            "--rate",
+            "0.5",
+            "--duration",
+            "30",
+            "--guesses",
+            "0",
+            "beef+unix:///tmp/x.socket",
+        ])
+        .unwrap();
+        assert_eq!(options.connections, 100);
+        assert_eq!(options.rate, 0.5);
+        assert_eq!(options.duration, Duration::from_secs(30));
+        assert_eq!(options.wrong_guesses, 0);
+        assert_eq!(
+            options.address,
+            Address::Unix(PathBuf::from("/tmp/x.socket"))
+        );
+    }
+
+    #[test]
+    fn bad_options_are_refused() {
+        assert!(options(&["--connections", "3"]).is_err());
+        assert!(options(&["--connections", "4.5"]).is_err());
+        assert!(options(&["--rate", "0"]).is_err());
+        assert!(options(&["--rate", "fast"]).is_err());
+        assert!(options(&["--guesses", "-1"]).is_err());
+        assert!(options(&["a03f"]).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use wguessr_client::{Client, ClientId, Event};
+
+use crate::stats::Stats;
+
+// what the pickers go through, the server doesn't check them against its dictionary
+const WORDS: [&str; 8] = [
+    "apple", "crane", "ghost", "pride", "smile", "table", "vivid", "zebra",
+];
+// how long to back off after the server said no
+const REFUSED_WAIT: Duration = Duration::from_secs(1);
+
+// how every pair plays
+#[derive(Clone, Copy)]
+pub struct Plan {
+    // between one guess and the next
+    pub every: Duration,
+    // how many wrong ones come before the right one
+    pub wrong_guesses: usize,
+    // nothing new is started after this
+    pub until: Instant,
+}
+
+// how the server answered a guess
+enum Answer {
+    Wrong,
+    Right,
+    // slowed down, the same guess goes again in a while
+    Refused,
+    // there's no beef to guess in anymore
+    Over,
+    Gone,
+}
+
+// one picks words and challenges, the other guesses, each on its own thread until the plan ends
+pub fn play(picker: Client, guesser: Client, plan: Plan, stats: &Arc<Mutex<Stats>>) {
+    let Some(guesser_id) = guesser.id() else {
+        return;
+    };
+    // the word the picker picked last, the guesser only finds out how long it is otherwise
+    let target = Arc::new(Mutex::new(WORDS[0]));
+    let picker_target = Arc::clone(&target);
+    let picker_stats = Arc::clone(stats);
+    thread::spawn(move || pick(picker, guesser_id, &picker_target, plan, &picker_stats));
+    let stats = Arc::clone(stats);
+    thread::spawn(move || guess(guesser, &target, plan, &stats));
+}
+
+fn pick(
+    mut picker: Client,
+    guesser: ClientId,
+    target: &Mutex<&'static str>,
+    plan: Plan,
+    stats: &Mutex<Stats>,
+) {
+    for word in WORDS.iter().cycle() {
+        if Instant::now() >= plan.until {
+            return;
+        }
+        *target.lock().unwrap() = word;
+        if picker.challenge(guesser, word).is_err() {
+            stats.lock().unwrap().disconnects += 1;
+            return;
+        }
+        // until the beef is over, or never started
+        loop {
+            let Ok(Some(line)) = picker.next_line() else {
+                stats.lock().unwrap().disconnects += 1;
+                return;
+            };
+            match Event::parse(&line) {
+                Event::BattleStarted(_) => stats.lock().unwrap().beefs_started += 1,
+                Event::Squashed => break,
+                Event::OpponentLeft(_) => return,
+                _ => {
+                    if let Some(trouble) = trouble(&line) {
+                        stats.lock().unwrap().error(trouble);
+                        thread::sleep(REFUSED_WAIT);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn guess(mut guesser: Client, target: &Mutex<&'static str>, plan: Plan, stats: &Mutex<Stats>) {
+    loop {
+        // waits to be challenged
+        let word = loop {
+            match guesser.next_event() {
+                Ok(Some(Event::Challenged(_))) => break *target.lock().unwrap(),
+                Ok(Some(_)) => {}
+                _ => {
+                    stats.lock().unwrap().disconnects += 1;
+                    return;
+                }
+            }
+        };
+        let wrong = WORDS.iter().filter(|wrong| **wrong != word).cycle();
+        let mut guesses = wrong.take(plan.wrong_guesses).chain([&word]).peekable();
+        let mut next = Instant::now();
+        while let Some(guess) = guesses.peek() {
+            thread::sleep(next.saturating_duration_since(Instant::now()));
+            let sent = Instant::now();
+            if sent >= plan.until {
+                return;
+            }
+            next = sent + plan.every;
+            if guesser.guess(guess).is_err() {
+                stats.lock().unwrap().disconnects += 1;
+                return;
+            }
+            stats.lock().unwrap().guesses_sent += 1;
+            match answer(&mut guesser, stats) {
+                Answer::Wrong => {}
+                Answer::Right => stats.lock().unwrap().beefs_finished += 1,
+                Answer::Refused => {
+                    next = sent + REFUSED_WAIT;
+                    continue;
+                }
+                Answer::Over => break,
+                Answer::Gone => {
+                    stats.lock().unwrap().disconnects += 1;
+                    return;
+                }
+            }
+            stats.lock().unwrap().latencies.push(sent.elapsed());
+            guesses.next();
+        }
+    }
+}
+
+// reads until the answer to the guess just sent
+fn answer(guesser: &mut Client, stats: &Mutex<Stats>) -> Answer {
+    loop {
+        let Ok(Some(line)) = guesser.next_line() else {
+            return Answer::Gone;
+        };
+        match Event::parse(&line) {
+            Event::WrongGuess(_) => return Answer::Wrong,
+            Event::Squashed => return Answer::Right,
+            _ => {
+                if let Some(trouble) = trouble(&line) {
+                    stats.lock().unwrap().error(trouble);
+                    return match trouble.starts_with("NO BEEF") {
+                        true => Answer::Over,
+                        false => Answer::Refused,
+                    };
+                }
+            }
+        }
+    }
+}
+
+// the server saying no, without how long to wait so the same kind is counted together
+fn trouble(line: &str) -> Option<&str> {
+    let line = line.strip_prefix("beef: ")?;
+    let refused = ["SLOW DOWN!", "CAN'T ", "NO BEEF"]
+        .iter()
+        .any(|start| line.starts_with(start));
+    refused.then(|| line.split(", TRY AGAIN").next().unwrap_or(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refusals_are_counted_by_kind() {
+        assert_eq!(
+            trouble("beef: SLOW DOWN! TOO MANY GUESSES, TRY AGAIN IN 1500ms"),
+            Some("SLOW DOWN! TOO MANY GUESSES")
+        );
+        assert_eq!(
+            trouble("beef: CAN'T BEEF, ALREADY BEEFING!"),
+            Some("CAN'T BEEF, ALREADY BEEFING!")
+        );
+        assert_eq!(trouble("beef: WRONG GUESS! _+__+"), None);
+        assert_eq!(trouble("a03f: CAN'T touch this"), None);
+    }
+}
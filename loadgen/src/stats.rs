@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+// everything counted while the load runs, shared by every player
+#[derive(Default)]
+pub struct Stats {
+    pub signed_in: usize,
+    pub beefs_started: usize,
+    pub beefs_finished: usize,
+    pub guesses_sent: usize,
+    // how long every answered guess took to be answered
+    pub latencies: Vec<Duration>,
+    // connections that went away before the end
+    pub disconnects: usize,
+    // what went wrong, by what the server or the connection said
+    pub errors: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    pub fn error(&mut self, what: &str) {
+        *self.errors.entry(what.to_string()).or_default() += 1;
+    }
+
+    // what to tell after running for elapsed with this many connections
+    pub fn report(&self, connections: usize, elapsed: Duration) -> String {
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        let answered = self.latencies.len();
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "connections  {} of {connections} signed in",
+            self.signed_in
+        );
+        let _ = writeln!(
+            report,
+            "beefs        {} started, {} finished, {:.1}/s",
+            self.beefs_started,
+            self.beefs_finished,
+            self.beefs_finished as f64 / seconds
+        );
+        let _ = writeln!(
+            report,
+            "guesses      {} sent, {answered} answered, {:.1}/s",
+            self.guesses_sent,
+            answered as f64 / seconds
+        );
+
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        let latency = match sorted.last() {
+            None => "no guess answered".to_string(),
+            Some(max) => format!(
+                "p50 {}  p90 {}  p99 {}  max {}",
+                millis(percentile(&sorted, 0.5)),
+                millis(percentile(&sorted, 0.9)),
+                millis(percentile(&sorted, 0.99)),
+                millis(*max)
+            ),
+        };
+        let _ = writeln!(report, "latency      {latency}");
+        let _ = writeln!(report, "disconnects  {}", self.disconnects);
+
+        let errors: Vec<_> = self
+            .errors
+            .iter()
+            .map(|(what, count)| format!("{count} {what}"))
+            .collect();
+        let errors = match errors.is_empty() {
+            true => "none".to_string(),
+            false => errors.join(", "),
+        };
+        let _ = writeln!(report, "errors       {errors}");
+        report
+    }
+}
+
+// the smallest latency at least this fraction of them are at or below, they have to be sorted
+// and there has to be one
+fn percentile(sorted: &[Duration], fraction: f64) -> Duration {
+    let rank = (sorted.len() as f64 * fraction).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_are_taken_by_rank() {
+        let sorted: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 0.5), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 0.99), Duration::from_millis(99));
+        assert_eq!(percentile(&sorted[..1], 0.9), Duration::from_millis(1));
+        assert_eq!(percentile(&sorted[..3], 0.0), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn report_has_every_number() {
+        let mut stats = Stats {
+            signed_in: 4,
+            beefs_started: 3,
+            beefs_finished: 2,
+            guesses_sent: 7,
+            latencies: vec![Duration::from_millis(2), Duration::from_micros(500)],
+            ..Stats::default()
+        };
+        stats.error("SLOW DOWN! TOO MANY GUESSES");
+        stats.error("SLOW DOWN! TOO MANY GUESSES");
+        assert_eq!(
+            stats.report(6, Duration::from_secs(2)),
+            "connections  4 of 6 signed in\n\
+             beefs        3 started, 2 finished, 1.0/s\n\
+             guesses      7 sent, 2 answered, 1.0/s\n\
+             latency      p50 0.50ms  p90 2.00ms  p99 2.00ms  max 2.00ms\n\
+             disconnects  0\n\
+             errors       2 SLOW DOWN! TOO MANY GUESSES\n"
+        );
+        assert!(Stats::default()
+            .report(0, Duration::ZERO)
+            .contains("no guess answered"));
+    }
+}
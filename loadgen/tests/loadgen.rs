@@ -0,0 +1,28 @@
+use std::process::Command;
+use std::{env, fs, process};
+
+use wguessr_server::config::Config;
+use wguessr_server::Server;
+
+#[test]
+fn plays_beefs_and_reports_them() {
+    let path = env::temp_dir().join(format!("wguessr-loadgen-{}.socket", process::id()));
+    let server = Server::start(Config::default(), "127.0.0.1:0", path.to_str().unwrap())
+        .expect("server didn't start");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_wguessr-loadgen"))
+        .args(["--connections", "4", "--rate", "2", "--duration", "1.5"])
+        .args(["--guesses", "1", "--unix"])
+        .arg(&server.socket_path)
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(&server.socket_path);
+    let report = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{report}");
+    assert!(report.contains("connections  4 of 4 signed in"), "{report}");
+    assert!(report.contains("disconnects  0"), "{report}");
+    // a beef is over after two guesses, at two guesses a second every pair gets through one
+    assert!(!report.contains(" 0 finished"), "{report}");
+    assert!(!report.contains("no guess answered"), "{report}");
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use crate::client::ClientId;
+use crate::limits::RateLimiter;
+
+// accounts outlive connections, so players can come back to the same id
+pub type Accounts = HashMap<ClientId, Account>;
+
+pub struct Account {
+    password: Vec<u8>,
+    pub friends: BTreeSet<ClientId>,
+    pub blocked: BTreeSet<ClientId>,
+    pub limiter: RateLimiter,
+}
+
+impl Account {
+    pub fn new(password: Vec<u8>) -> Self {
+        Account {
+            password,
+            friends: BTreeSet::new(),
+            blocked: BTreeSet::new(),
+            limiter: RateLimiter::default(),
+        }
+    }
+
+    pub fn check_password(&self, password: &Vec<u8>) -> bool {
+        password.eq(&self.password)
+    }
+
+    // someone is either a friend or blocked, never both
+    pub fn befriend(&mut self, id: ClientId) -> bool {
+        self.blocked.remove(&id);
+        self.friends.insert(id)
+    }
+
+    pub fn block(&mut self, id: ClientId) -> bool {
+        self.friends.remove(&id);
+        self.blocked.insert(id)
+    }
+
+    pub fn forget(&mut self, id: &ClientId) -> bool {
+        self.friends.remove(id) | self.blocked.remove(id)
+    }
+}
+
+// one account a line: id, password as hex, then friends and blocked users, `-` for nobody
+fn to_line(id: &ClientId, account: &Account) -> String {
+    let ids = |ids: &BTreeSet<ClientId>| match ids.is_empty() {
+        true => "-".to_string(),
+        false => ids
+            .iter()
+            .map(|id| format!("{id:04x}"))
+            .collect::<Vec<String>>()
+            .join(","),
+    };
+    let password: String = account
+        .password
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!(
+        "{id:04x} {password} {} {}",
+        ids(&account.friends),
+        ids(&account.blocked)
+    )
+}
+
+fn from_line(line: &str) -> Option<(ClientId, Account)> {
+    let ids = |ids: &str| -> Option<BTreeSet<ClientId>> {
+        match ids {
+            "-" => Some(BTreeSet::new()),
+            ids => ids
+                .split(',')
+                .map(|id| ClientId::from_str_radix(id, 16).ok())
+                .collect(),
+        }
+    };
+    let [id, password, friends, blocked] = line.split(' ').collect::<Vec<&str>>()[..] else {
+        return None;
+    };
+    let password = (0..password.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(password.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let mut account = Account::new(password);
+    account.friends = ids(friends)?;
+    account.blocked = ids(blocked)?;
+    Some((ClientId::from_str_radix(id, 16).ok()?, account))
+}
+
+// a missing file is a server that hasn't had anyone sign up yet
+pub fn load(path: &Path) -> io::Result<Accounts> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Accounts::new()),
+        Err(error) => return Err(error),
+    };
+    text.lines()
+        .enumerate()
+        .map(|(number, line)| {
+            from_line(line).ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {} isn't an account", number + 1),
+                )
+            })
+        })
+        .collect()
+}
+
+// written next to the old file and moved over it, so a crash halfway leaves the old one
+pub fn save(accounts: &Accounts, path: &Path) -> io::Result<()> {
+    let mut lines: Vec<String> = accounts
+        .iter()
+        .map(|(id, account)| to_line(id, account) + "\n")
+        .collect();
+    lines.sort();
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, lines.concat())?;
+    fs::rename(temporary, path)
+}
+
+pub trait AccountDatabase {
+    fn has_blocked(&self, id: &ClientId, other: &ClientId) -> bool;
+    fn blocked_by(&self, id: &ClientId) -> Vec<ClientId>;
+}
+
+impl AccountDatabase for Accounts {
+    fn has_blocked(&self, id: &ClientId, other: &ClientId) -> bool {
+        self.get(id)
+            .is_some_and(|account| account.blocked.contains(other))
+    }
+
+    // everyone who doesn't want to hear from `id`
+    fn blocked_by(&self, id: &ClientId) -> Vec<ClientId> {
+        self.iter()
+            .filter(|(_, account)| account.blocked.contains(id))
+            .map(|(other, _)| *other)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn accounts_survive_a_save_and_load() {
+        let mut accounts = Accounts::new();
+        let mut account = Account::new(b"hunter 2".to_vec());
+        account.befriend(0x7b69);
+        account.block(0x0001);
+        accounts.insert(0xa03f, account);
+        accounts.insert(0x0002, Account::new(Vec::new()));
+
+        let path = env::temp_dir().join(format!("wguessr-accounts-{}", process::id()));
+        save(&accounts, &path).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let account = &loaded[&0xa03f];
+        assert!(account.check_password(&b"hunter 2".to_vec()));
+        assert_eq!(account.friends, BTreeSet::from([0x7b69]));
+        assert_eq!(account.blocked, BTreeSet::from([0x0001]));
+        assert!(loaded[&0x0002].check_password(&Vec::new()));
+        assert!(load(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn broken_lines_are_refused() {
+        assert!(from_line("a03f 7 - -").is_none());
+        assert!(from_line("a03f 70 nope -").is_none());
+        assert!(from_line("a03f 70 -").is_none());
+        assert!(from_line("a03f 70 7b69 -").is_some());
+    }
+}
@@ -0,0 +1,66 @@
+use std::io;
+use std::io::BufRead;
+
+use beef_messages::TournamentId;
+
+use crate::tournament::{start_pending_matches, Format, TournamentDatabase};
+use crate::Stores;
+
+const HELP: &str = "admin commands:
+    tournament <elimination|roundrobin> <name>  creates a tournament players can join
+    start <id>                                  closes registration and starts round one
+    tournaments                                 lists all tournaments
+    shutdown                                    counts down and stops the server, same as ctrl-c";
+
+// whoever runs the server is the admin, commands come in on stdin
+pub fn run_console(stores: Stores) {
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match command {
+            "" => {}
+            "tournament" => {
+                let (format, name) = rest.split_once(' ').unwrap_or((rest, ""));
+                match (Format::parse(format), name.trim()) {
+                    (None, _) => println!("unknown format {format}\n{HELP}"),
+                    (_, "") => println!("a tournament needs a name\n{HELP}"),
+                    (Some(format), name) => {
+                        let id = stores
+                            .tournaments
+                            .lock()
+                            .unwrap()
+                            .create(name.to_string(), format);
+                        println!("created tournament #{id:x}, players join with j<id>");
+                    }
+                }
+            }
+            "start" => {
+                let Ok(id) = TournamentId::from_str_radix(rest.trim(), 16) else {
+                    println!("start needs a tournament id\n{HELP}");
+                    continue;
+                };
+                let started = match stores.tournaments.lock().unwrap().get_tournament(id) {
+                    Some(tournament) => tournament.start().map_err(|error| error.to_string()),
+                    None => Err(format!("no tournament #{id:x}")),
+                };
+                match started {
+                    Ok(_) => {
+                        println!("tournament #{id:x} started");
+                        stores.game.run(start_pending_matches);
+                    }
+                    Err(error) => println!("can't start tournament #{id:x}: {error}"),
+                }
+            }
+            "shutdown" => {
+                stores.shutdown.request();
+                break;
+            }
+            "tournaments" => {
+                println!("{}", stores.tournaments.lock().unwrap().to_summary_string());
+            }
+            _ => println!("{HELP}"),
+        }
+    }
+}
@@ -1,15 +1,46 @@
 use std::collections::HashSet;
+use std::time::SystemTime;
+
+pub use beef_messages::hint;
+use beef_messages::TournamentId;
 
 use crate::client::ClientId;
+use crate::daily::DAILY_GUESSES;
+use crate::http::escape_html;
 
 pub type Battles = HashSet<Battle>;
 pub type BattleId = (ClientId, ClientId);
 
+// as many as wordle gives you
+pub const PRACTICE_GUESSES: usize = 6;
+
+#[derive(Eq, Hash, PartialEq, Clone, Copy)]
+pub enum BattleKind {
+    /// Between two players, the master picked the word
+    Duel,
+    /// The server picked the word, the player is on their own
+    Practice,
+    /// Word of the day, the same one for everybody
+    Daily,
+    /// Tournament match, both players race to guess a word picked by the server
+    Tournament(TournamentId),
+}
+
+#[derive(Eq, Hash, PartialEq, Clone)]
+pub struct Guess {
+    pub by: ClientId,
+    pub word: String,
+    pub at: SystemTime,
+}
+
 #[derive(Eq, Hash, PartialEq, Clone)]
 pub struct Battle {
     pub id: BattleId,
     pub target: String,
-    previous_guesses: Vec<String>,
+    pub started: SystemTime,
+    pub kind: BattleKind,
+    max_guesses: Option<usize>,
+    previous_guesses: Vec<Guess>,
 }
 
 impl Battle {
@@ -17,29 +48,112 @@ impl Battle {
         Battle {
             id: (master, player),
             target,
+            started: SystemTime::now(),
+            kind: BattleKind::Duel,
+            max_guesses: None,
             previous_guesses: Vec::new(),
         }
     }
+
+    // nobody to beef with, so the player is on both sides of the battle
+    pub fn practice(player: ClientId, target: String) -> Self {
+        Battle {
+            kind: BattleKind::Practice,
+            max_guesses: Some(PRACTICE_GUESSES),
+            ..Battle::new(player, player, target)
+        }
+    }
+
+    // resumes with the guesses from earlier, players get one go at the daily
+    pub fn daily(player: ClientId, target: String, guesses: Vec<Guess>) -> Self {
+        Battle {
+            kind: BattleKind::Daily,
+            max_guesses: Some(DAILY_GUESSES),
+            previous_guesses: guesses,
+            ..Battle::new(player, player, target)
+        }
+    }
+
+    pub fn tournament(a: ClientId, b: ClientId, target: String, id: TournamentId) -> Self {
+        Battle {
+            kind: BattleKind::Tournament(id),
+            ..Battle::new(a, b, target)
+        }
+    }
+
+    // in a duel only the player guesses, everything else is guessed by whoever is in it
+    pub fn can_guess(&self, id: &ClientId) -> bool {
+        match self.kind {
+            BattleKind::Duel => self.id.1.eq(id),
+            _ => true,
+        }
+    }
+
+    pub fn is_solo(&self) -> bool {
+        self.id.0.eq(&self.id.1)
+    }
     fn to_html_string(&self) -> String {
+        // everyone gets the same daily, so it would be spoiled for whoever is watching
+        if let BattleKind::Daily = self.kind {
+            return format!("<div style=\"border: 1px dotted #dddddd; margin: 1em; padding: 1em; max-width: 30em;\">
+            <span><strong>#{:?}</strong> is on the daily</span>
+        </div>",
+                self.id.0);
+        }
+        // both players are guessing, so neither of them gets to see the word
+        if let BattleKind::Tournament(id) = self.kind {
+            return format!("<div style=\"border: 1px dotted #dddddd; margin: 1em; padding: 1em; max-width: 30em;\">
+            <span><strong>#{:?}</strong> vs. <strong>#{:?}</strong> in <a style=\"color: #ff6666\" href=\"/tournaments\">tournament #{id:x}</a></span>
+        </div>",
+                self.id.0, self.id.1);
+        }
+        if self.is_solo() {
+            return format!("<div style=\"border: 1px dotted #dddddd; margin: 1em; padding: 1em; max-width: 30em;\">
+            <span><strong>#{:?}</strong> is practicing</span><br><hr>
+            <span>🡒 <strong>{}</strong></span><span> 🡐 </span><span>{}</span>
+        </div>",
+                self.id.0, escape_html(self.target.trim()), self.previous_guesses());
+        }
         format!("<div style=\"border: 1px dotted #dddddd; margin: 1em; padding: 1em; max-width: 30em;\">
             <span><strong>#{:?}</strong> vs. <strong>#{:?}</strong></span><br><hr>
             <span>🡒 <strong>{}</strong></span><span> 🡐 </span><span>{}</span>
         </div>",
-                self.id.0, self.id.1, self.target.trim(), self.previous_guesses())
+                self.id.0, self.id.1, escape_html(self.target.trim()), self.previous_guesses())
     }
 
-    pub fn check_guess(&mut self, guess: String) -> bool {
-        if self.target.eq(&guess) {
-            return true;
-        }
-        self.previous_guesses.push(guess);
-        false
+    // every guess is kept (the correct one included), so finished battles can be replayed
+    pub fn check_guess(&mut self, by: ClientId, guess: String) -> bool {
+        let is_correct = self.target.trim().eq_ignore_ascii_case(guess.trim());
+        self.previous_guesses.push(Guess {
+            by,
+            word: guess,
+            at: SystemTime::now(),
+        });
+        is_correct
+    }
+
+    pub fn hint(&self, guess: &str) -> String {
+        hint(&self.target, guess)
     }
 
+    pub fn letters(&self) -> usize {
+        self.target.trim().chars().count()
+    }
+
+    pub fn guesses_left(&self) -> Option<usize> {
+        self.max_guesses
+            .map(|max| max.saturating_sub(self.previous_guesses.len()))
+    }
+
+    pub fn guesses(&self) -> &[Guess] {
+        &self.previous_guesses
+    }
+
+    // guesses are whatever players typed, so they're escaped for the overview page
     fn previous_guesses(&self) -> String {
         self.previous_guesses
             .iter()
-            .map(|guess| guess.trim().to_string())
+            .map(|guess| escape_html(guess.word.trim()))
             .collect::<Vec<String>>()
             .join(", ")
     }
@@ -56,7 +170,7 @@ impl Battle {
 pub trait BattleDatabase {
     fn add_battle(&mut self, battle: Battle);
     fn del_battle(&mut self, battle_id: &BattleId);
-    fn del_battle_by_client(&mut self, client_id: &ClientId) -> Option<ClientId>;
+    fn del_battle_by_client(&mut self, client_id: &ClientId) -> Option<Battle>;
     fn to_html_string(&self) -> String;
     fn update_or_add_battle(&mut self, battle: Battle);
     fn exists_by_id(&self, client_id: &ClientId) -> bool;
@@ -71,15 +185,14 @@ impl BattleDatabase for Battles {
     fn del_battle(&mut self, battle_id: &BattleId) {
         self.retain(|battle| !battle.id.eq(battle_id));
     }
-    fn del_battle_by_client(&mut self, client_id: &ClientId) -> Option<ClientId> {
+    fn del_battle_by_client(&mut self, client_id: &ClientId) -> Option<Battle> {
         if let Some(battle_found) = self
             .iter()
             .find(|&battle| battle.id.0.eq(client_id) || battle.id.1.eq(client_id))
             .cloned()
         {
             self.retain(|battle| !battle.id.0.eq(client_id) && !battle.id.1.eq(client_id));
-            let other_id = battle_found.get_opponnent(client_id);
-            return Some(other_id);
+            return Some(battle_found);
         }
         None
     }
@@ -98,7 +211,7 @@ impl BattleDatabase for Battles {
 
     fn exists_by_id(&self, client_id: &ClientId) -> bool {
         self.iter()
-            .any(|battle| battle.id.0.eq(client_id) || battle.id.0.eq(client_id))
+            .any(|battle| battle.id.0.eq(client_id) || battle.id.1.eq(client_id))
     }
 
     fn get_current_battle(&self, client_id: &ClientId) -> Option<Battle> {
@@ -107,3 +220,22 @@ impl BattleDatabase for Battles {
             .cloned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_are_escaped_on_the_overview() {
+        let mut battle = Battle::new(1, 2, "<b>".into());
+        battle.check_guess(2, "<script>".into());
+        let html = battle.to_html_string();
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+
+        let mut practice = Battle::new(1, 1, "crane".into());
+        practice.check_guess(1, "<i>".into());
+        assert!(practice.to_html_string().contains("&lt;i&gt;"));
+    }
+}
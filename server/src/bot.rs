@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+use std::{io, thread};
+
+use crate::battle::hint;
+use crate::client::ClientDatabase;
+use crate::dictionary::{random_number, words};
+use crate::game::GameHandle;
+
+// bots answer instantly otherwise, which is no fun to play against
+const THINKING_TIME: Duration = Duration::from_millis(800);
+
+#[derive(Clone, Copy)]
+pub enum Difficulty {
+    /// Guesses any word that fits the latest hint
+    Easy,
+    /// Guesses the word with the most common letters among those that fit every hint
+    Medium,
+    /// Guesses the word whose hint is expected to rule out the most words
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+// bots connect and play like any other client, just from their own end of a socket pair, so
+// they go through exactly the same paths as players do
+pub fn spawn_bot(stream: UnixStream, difficulty: Difficulty, game: GameHandle) {
+    thread::spawn(move || {
+        if let Err(error) = run_bot(stream, difficulty, game) {
+            println!("bot stopped: {error}");
+        }
+    });
+}
+
+fn run_bot(stream: UnixStream, difficulty: Difficulty, game: GameHandle) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+
+    // the password has to wait for the prompt, the server drops anything sent with the handshake
+    writer.write_all(b"beef\r\n\r\n")?;
+    for line in lines.by_ref() {
+        if line?.contains("AUTH NEW") {
+            break;
+        }
+    }
+    writer.write_all(format!("m{:x}", random_number()).as_bytes())?;
+    for line in lines.by_ref() {
+        let line = line?;
+        let Some(id) = line
+            .split_once("WELCOME, ")
+            .and_then(|(_, rest)| rest.strip_suffix('!'))
+            .and_then(|id| u16::from_str_radix(id, 16).ok())
+        else {
+            continue;
+        };
+        game.run(move |game| game.clients.set_bot(&id, difficulty));
+        break;
+    }
+
+    let mut solver: Option<Solver> = None;
+    for line in lines {
+        let line = line?;
+        let line = line.trim();
+        if line == "beef: PING" {
+            writer.write_all(b"o")?;
+            continue;
+        }
+        if let Some(letters) = line
+            .strip_prefix("beef: THE WORD HAS ")
+            .and_then(|rest| rest.strip_suffix(" LETTERS"))
+            .and_then(|letters| letters.parse().ok())
+        {
+            solver = Some(Solver::new(difficulty, letters));
+        } else if let Some(feedback) = line.strip_prefix("beef: WRONG GUESS! ") {
+            if let Some(solver) = solver.as_mut() {
+                solver.learn(feedback);
+            }
+        } else {
+            if line.contains("SQUASHED") || line.contains("FORFEIT") {
+                solver = None;
+            }
+            continue;
+        }
+
+        let Some(solver) = solver.as_mut() else {
+            continue;
+        };
+        thread::sleep(THINKING_TIME);
+        match solver.next_guess() {
+            Some(guess) => writer.write_all(format!("g{guess}").as_bytes())?,
+            // the word isn't in the dictionary, there is nothing left to guess
+            None => writer.write_all(b"f")?,
+        }
+    }
+    Ok(())
+}
+
+pub struct Solver {
+    difficulty: Difficulty,
+    // every dictionary word of the right length, guesses don't have to be possible answers
+    pool: Vec<&'static str>,
+    candidates: Vec<&'static str>,
+    guessed: Vec<&'static str>,
+}
+
+impl Solver {
+    pub fn new(difficulty: Difficulty, letters: usize) -> Self {
+        let pool: Vec<&str> = words()
+            .filter(|word| word.chars().count() == letters)
+            .collect();
+        Solver {
+            difficulty,
+            candidates: pool.clone(),
+            pool,
+            guessed: Vec::new(),
+        }
+    }
+
+    // narrows the candidates down to the words that would have given the same hint
+    pub fn learn(&mut self, feedback: &str) {
+        let Some(guess) = self.guessed.last().copied() else {
+            return;
+        };
+        if let Difficulty::Easy = self.difficulty {
+            self.candidates = self.pool.clone();
+        }
+        self.candidates
+            .retain(|candidate| hint(candidate, guess).eq(feedback));
+        self.candidates
+            .retain(|candidate| !self.guessed.contains(candidate));
+    }
+
+    pub fn next_guess(&mut self) -> Option<&'static str> {
+        let guess = match self.difficulty {
+            Difficulty::Easy => self.random_candidate(),
+            Difficulty::Medium => self.most_frequent_letters(),
+            Difficulty::Hard => self.most_information(),
+        }?;
+        self.guessed.push(guess);
+        Some(guess)
+    }
+
+    fn random_candidate(&self) -> Option<&'static str> {
+        if self.candidates.is_empty() {
+            return None;
+        }
+        let index = random_number() as usize % self.candidates.len();
+        Some(self.candidates[index])
+    }
+
+    fn most_frequent_letters(&self) -> Option<&'static str> {
+        let mut frequencies: HashMap<char, usize> = HashMap::new();
+        for letter in self.candidates.iter().flat_map(|word| word.chars()) {
+            *frequencies.entry(letter).or_default() += 1;
+        }
+        self.candidates.iter().copied().max_by_key(|word| {
+            let mut letters: Vec<char> = word.chars().collect();
+            letters.sort();
+            letters.dedup();
+            letters
+                .iter()
+                .map(|letter| frequencies.get(letter).unwrap_or(&0))
+                .sum::<usize>()
+        })
+    }
+
+    // entropy of the hints a guess would produce over the remaining candidates, the more evenly
+    // they get split up the fewer are expected to remain
+    fn most_information(&self) -> Option<&'static str> {
+        if self.candidates.len() <= 2 {
+            return self.candidates.first().copied();
+        }
+        let entropy = |guess: &str| {
+            let mut partitions: HashMap<String, usize> = HashMap::new();
+            for candidate in &self.candidates {
+                *partitions.entry(hint(candidate, guess)).or_default() += 1;
+            }
+            let total = self.candidates.len() as f64;
+            partitions
+                .values()
+                .map(|count| {
+                    let p = *count as f64 / total;
+                    -p * p.log2()
+                })
+                .sum::<f64>()
+        };
+        self.pool
+            .iter()
+            .copied()
+            .filter(|word| !self.guessed.contains(word))
+            .map(|word| {
+                // prefer possible answers on ties, they might just be right
+                let bonus = if self.candidates.contains(&word) {
+                    1.0 / self.candidates.len() as f64
+                } else {
+                    0.0
+                };
+                (word, entropy(word) + bonus)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(word, _)| word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(difficulty: Difficulty, target: &str) -> usize {
+        let mut solver = Solver::new(difficulty, target.len());
+        for round in 1..=50 {
+            let guess = solver.next_guess().expect("ran out of words");
+            if guess.eq(target) {
+                return round;
+            }
+            solver.learn(&hint(target, guess));
+        }
+        panic!("{target} not solved");
+    }
+
+    #[test]
+    fn solvers_find_dictionary_words() {
+        for target in ["crane", "youth", "about", "water"] {
+            solve(Difficulty::Easy, target);
+            assert!(solve(Difficulty::Medium, target) <= 10);
+            assert!(solve(Difficulty::Hard, target) <= 6);
+        }
+    }
+
+    #[test]
+    fn solver_gives_up_on_unknown_words() {
+        let mut solver = Solver::new(Difficulty::Medium, 5);
+        while let Some(guess) = solver.next_guess() {
+            solver.learn(&hint("zzzzz", guess));
+        }
+        assert!(solver.candidates.is_empty());
+    }
+}
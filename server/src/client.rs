@@ -1,17 +1,33 @@
 use std::collections::HashMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+use beef_messages::escape_json;
+
+use crate::bot::Difficulty;
+use crate::error::{ServerError, ServerResult};
 use crate::generic_stream::GenericStream;
+use crate::http::escape_html;
+
+// nothing sent for this long and a player shows up as away
+pub const IDLE_AFTER: Duration = Duration::from_secs(5 * 60);
 
 pub type Clients = HashMap<ClientId, Client>;
 
 pub trait ClientDatabase {
     fn remove_user(&mut self, id: &ClientId);
-    fn get_stream(&self, id: &ClientId) -> Arc<GenericStream>;
+    fn get_stream(&self, id: &ClientId) -> ServerResult<Arc<GenericStream>>;
+    fn send_to(&self, id: &ClientId, msg: &str) -> ServerResult<()>;
     fn to_html_string(&self) -> String;
     fn update_battle_status(&mut self, m_id: &ClientId, p_id: &ClientId, status: bool);
     fn remove_battle_status(&mut self, m_id: &ClientId);
+    fn set_bot(&mut self, id: &ClientId, difficulty: Difficulty);
+    fn is_bot(&self, id: &ClientId) -> bool;
+    fn touch(&mut self, id: &ClientId);
+    fn set_status(&mut self, id: &ClientId, status: Presence, message: Option<String>);
+    fn get_presence(&self, id: &ClientId) -> Option<Presence>;
+    fn to_json_string(&self) -> String;
     fn get_ids(&self) -> String;
 }
 
@@ -20,23 +36,37 @@ impl ClientDatabase for Clients {
         self.remove(id);
     }
 
-    fn get_stream(&self, id: &ClientId) -> Arc<GenericStream> {
-        Arc::clone(
-            &self
-                .get(id)
-                .unwrap_or_else(|| panic!("id {id} not found"))
-                .stream,
-        )
+    fn get_stream(&self, id: &ClientId) -> ServerResult<Arc<GenericStream>> {
+        match self.get(id) {
+            Some(client) => Ok(Arc::clone(&client.stream)),
+            None => Err(ServerError::NotOnline(*id)),
+        }
+    }
+
+    // tells who couldn't be reached apart from who isn't there at all
+    fn send_to(&self, id: &ClientId, msg: &str) -> ServerResult<()> {
+        self.get_stream(id)?
+            .send_msg(msg)
+            .map_err(|error| match error {
+                ServerError::Io(error) => ServerError::Undelivered(*id, error),
+                error => error,
+            })
     }
     fn to_html_string(&self) -> String {
         self.iter()
             .map(|(id, client)| {
                 let id = to_hex_str(id);
-                if client.is_battling {
-                    format!("<li> {id} ⚔️</li>")
-                } else {
-                    format!("<li> {id} </li>")
-                }
+                let bot = if client.bot.is_some() { "🤖" } else { "" };
+                let presence = client.presence();
+                let message = match &client.message {
+                    Some(message) => format!(" <em>{}</em>", escape_html(message)),
+                    None => String::new(),
+                };
+                format!(
+                    "<li> {id} {bot}{} <small>{}</small>{message}</li>",
+                    presence.icon(),
+                    presence.name()
+                )
             })
             .collect::<Vec<String>>()
             .join("")
@@ -57,9 +87,69 @@ impl ClientDatabase for Clients {
         }
     }
 
+    fn set_bot(&mut self, id: &ClientId, difficulty: Difficulty) {
+        if let Some(player) = self.get_mut(id) {
+            player.bot = Some(difficulty);
+        }
+    }
+
+    fn is_bot(&self, id: &ClientId) -> bool {
+        self.get(id).is_some_and(|client| client.bot.is_some())
+    }
+
+    fn touch(&mut self, id: &ClientId) {
+        if let Some(client) = self.get_mut(id) {
+            client.last_active = SystemTime::now();
+        }
+    }
+
+    fn set_status(&mut self, id: &ClientId, status: Presence, message: Option<String>) {
+        if let Some(client) = self.get_mut(id) {
+            client.status = status;
+            client.message = message;
+        }
+    }
+
+    fn get_presence(&self, id: &ClientId) -> Option<Presence> {
+        self.get(id).map(|client| client.presence())
+    }
+
+    fn to_json_string(&self) -> String {
+        let users = self
+            .iter()
+            .map(|(id, client)| {
+                let bot = match client.bot {
+                    Some(difficulty) => format!("\"{}\"", difficulty.name()),
+                    None => "null".to_string(),
+                };
+                let message = match &client.message {
+                    Some(message) => format!("\"{}\"", escape_json(message)),
+                    None => "null".to_string(),
+                };
+                format!(
+                    "{{\"id\":\"{}\",\"bot\":{bot},\"presence\":\"{}\",\"message\":{message}}}",
+                    to_hex_str(id),
+                    client.presence().name()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        format!("[{users}]")
+    }
+
     fn get_ids(&self) -> String {
         self.iter()
-            .map(|(id, _)| to_hex_str(id))
+            .map(|(id, client)| {
+                let mut details = Vec::new();
+                if let Some(difficulty) = client.bot {
+                    details.push(format!("bot, {}", difficulty.name()));
+                }
+                details.push(client.presence().name().to_string());
+                if let Some(message) = &client.message {
+                    details.push(message.clone());
+                }
+                format!("{} ({})", to_hex_str(id), details.join(", "))
+            })
             .collect::<Vec<String>>()
             .join("\n")
     }
@@ -67,23 +157,74 @@ impl ClientDatabase for Clients {
 
 pub type ClientId = u16;
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    Available,
+    Away,
+    DoNotDisturb,
+    InBattle,
+}
+
+impl Presence {
+    // only the ones players can pick themselves, being in a battle is up to the server
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "available" => Some(Presence::Available),
+            "away" => Some(Presence::Away),
+            "dnd" => Some(Presence::DoNotDisturb),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Presence::Available => "available",
+            Presence::Away => "away",
+            Presence::DoNotDisturb => "dnd",
+            Presence::InBattle => "in battle",
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Presence::Available => "🟢",
+            Presence::Away => "💤",
+            Presence::DoNotDisturb => "⛔",
+            Presence::InBattle => "⚔️",
+        }
+    }
+}
+
 pub struct Client {
-    password: Vec<u8>,
     pub stream: Arc<GenericStream>,
     is_battling: bool,
+    bot: Option<Difficulty>,
+    // what the player picked, shown unless they're battling or idle
+    status: Presence,
+    message: Option<String>,
+    last_active: SystemTime,
 }
 
 impl Client {
-    pub fn new(password: Vec<u8>, stream: GenericStream) -> Self {
+    pub fn new(stream: GenericStream) -> Self {
         Client {
-            password,
             stream: Arc::new(stream),
             is_battling: false,
+            bot: None,
+            status: Presence::Available,
+            message: None,
+            last_active: SystemTime::now(),
         }
     }
 
-    pub fn check_password(&self, password: &Vec<u8>) -> bool {
-        password.eq(&self.password)
+    pub fn presence(&self) -> Presence {
+        let idle = self.last_active.elapsed().unwrap_or_default() > IDLE_AFTER;
+        match (self.is_battling, self.status) {
+            (true, _) => Presence::InBattle,
+            // bots never sleep
+            (false, Presence::Available) if idle && self.bot.is_none() => Presence::Away,
+            (false, status) => status,
+        }
     }
 
     pub fn set_battling(&mut self, is_battling: bool) {
@@ -101,3 +242,24 @@ pub fn to_hex_str(id: &ClientId) -> String {
     let u8s: [u8; 2] = id.to_be_bytes();
     format!("{:02x}{:02x}", u8s[0], u8s[1])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_stream::duplex;
+
+    #[test]
+    fn send_to_tells_gone_from_unreachable() {
+        let mut clients = Clients::new();
+        let (server_end, client_end) = duplex();
+        clients.insert(1, Client::new(GenericStream::new(server_end)));
+        assert!(matches!(
+            clients.send_to(&2, "hi"),
+            Err(ServerError::NotOnline(2))
+        ));
+
+        drop(client_end);
+        let error = clients.send_to(&1, "hi").unwrap_err();
+        assert_eq!(error.unreachable(), Some(1));
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{env, fs};
+
+use beef_messages::Limited;
+
+// read from the working directory, unless WGUESSR_CONFIG points somewhere else
+const DEFAULT_PATH: &str = "wguessr.conf";
+
+// a token bucket, `burst` commands at once and `rate` more every second after that
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    pub rate: f64,
+    pub burst: f64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    pub guesses: Limit,
+    pub chat: Limit,
+    pub challenges: Limit,
+    // this many slow downs within the window and the connection gets dropped
+    pub max_violations: usize,
+    pub violation_window: Duration,
+    // a connection quiet for this long gets pinged, and dropped if it stays quiet as long again
+    pub heartbeat_interval: Duration,
+    // the game thread writes to every player itself, so it only waits this long on a stuck one
+    pub write_timeout: Duration,
+    // how long players get to wrap up once the server is told to stop
+    pub shutdown_countdown: Duration,
+    // connections handled at once, and how many more can wait for a free worker
+    pub workers: usize,
+    pub backlog: usize,
+    // where accounts are kept across restarts, None keeps them in memory only
+    pub accounts_file: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            guesses: Limit {
+                rate: 2.0,
+                burst: 5.0,
+            },
+            chat: Limit {
+                rate: 1.0,
+                burst: 5.0,
+            },
+            challenges: Limit {
+                rate: 0.2,
+                burst: 3.0,
+            },
+            max_violations: 10,
+            violation_window: Duration::from_secs(60),
+            heartbeat_interval: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(1),
+            shutdown_countdown: Duration::from_secs(10),
+            workers: 64,
+            backlog: 16,
+            accounts_file: None,
+        }
+    }
+}
+
+impl Config {
+    // a missing file is fine, a broken one gets reported and the defaults are used instead
+    pub fn load() -> Self {
+        let path = env::var("WGUESSR_CONFIG").unwrap_or(DEFAULT_PATH.to_string());
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Config::default();
+        };
+        match Config::parse(&text) {
+            Ok(config) => {
+                println!("config loaded from {path}");
+                config
+            }
+            Err(error) => {
+                println!("{path}: {error}, using the defaults");
+                Config::default()
+            }
+        }
+    }
+
+    // `key = value` lines, anything after a # is a comment
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Config::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected key = value", number + 1));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let number = |value: &str| -> Result<f64, String> {
+                match value.parse::<f64>() {
+                    Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+                    _ => Err(format!("{key} has to be a positive number")),
+                }
+            };
+            // a bucket that can't hold a whole token never lets anything through
+            let burst = |value: &str| -> Result<f64, String> {
+                match number(value)? {
+                    value if value >= 1.0 => Ok(value),
+                    _ => Err(format!("{key} has to be at least 1")),
+                }
+            };
+            let count = |value: &str| -> Result<usize, String> {
+                match value.parse::<usize>() {
+                    Ok(value) if value > 0 => Ok(value),
+                    _ => Err(format!("{key} has to be a positive whole number")),
+                }
+            };
+            match key {
+                "guesses.rate" => config.guesses.rate = number(value)?,
+                "guesses.burst" => config.guesses.burst = burst(value)?,
+                "chat.rate" => config.chat.rate = number(value)?,
+                "chat.burst" => config.chat.burst = burst(value)?,
+                "challenges.rate" => config.challenges.rate = number(value)?,
+                "challenges.burst" => config.challenges.burst = burst(value)?,
+                "max_violations" => config.max_violations = count(value)?,
+                "violation_window" => {
+                    config.violation_window = Duration::from_secs_f64(number(value)?)
+                }
+                "heartbeat_interval" => {
+                    config.heartbeat_interval = Duration::from_secs_f64(number(value)?)
+                }
+                "write_timeout" => config.write_timeout = Duration::from_secs_f64(number(value)?),
+                "shutdown_countdown" => {
+                    config.shutdown_countdown = Duration::from_secs(count(value)? as u64)
+                }
+                "workers" => config.workers = count(value)?,
+                "backlog" => config.backlog = count(value)?,
+                "accounts_file" => config.accounts_file = Some(PathBuf::from(value)),
+                _ => return Err(format!("unknown key {key}")),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn limit(&self, limited: Limited) -> Limit {
+        match limited {
+            Limited::Guesses => self.guesses,
+            Limited::Chat => self.chat,
+            Limited::Challenges => self.challenges,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_overrides_defaults() {
+        let config = Config::parse(
+            "# slower chat
+            chat.rate = 0.5
+            max_violations = 3 # kick early",
+        )
+        .unwrap();
+        assert_eq!(config.chat.rate, 0.5);
+        assert_eq!(config.chat.burst, Config::default().chat.burst);
+        assert_eq!(config.max_violations, 3);
+    }
+
+    #[test]
+    fn parse_rejects_nonsense() {
+        assert!(Config::parse("chat.rate = fast").is_err());
+        assert!(Config::parse("chat.rate = 0").is_err());
+        assert!(Config::parse("speed = 1").is_err());
+        assert!(Config::parse("chat.rate").is_err());
+        assert!(Config::parse("chat.rate = inf").is_err());
+    }
+
+    #[test]
+    fn parse_wants_whole_counts_and_full_tokens() {
+        assert!(Config::parse("workers = 2.5").is_err());
+        assert!(Config::parse("backlog = -1").is_err());
+        assert!(Config::parse("max_violations = 0").is_err());
+        assert!(Config::parse("shutdown_countdown = 0.5").is_err());
+        assert!(Config::parse("guesses.burst = 0.5").is_err());
+        assert!(Config::parse("chat.rate = -2").is_err());
+
+        let config = Config::parse(
+            "workers = 8
+            accounts_file = /var/lib/wguessr/accounts
+            write_timeout = 0.5
+            guesses.burst = 1
+            challenges.rate = 0.1",
+        )
+        .unwrap();
+        assert_eq!(config.workers, 8);
+        assert_eq!(
+            config.accounts_file,
+            Some(PathBuf::from("/var/lib/wguessr/accounts"))
+        );
+        assert_eq!(config.write_timeout, Duration::from_millis(500));
+        assert_eq!(config.guesses.burst, 1.0);
+        assert_eq!(config.challenges.rate, 0.1);
+    }
+}
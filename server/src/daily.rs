@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::battle::{hint, Guess};
+use crate::client::{to_hex_str, ClientId};
+use crate::dictionary::words;
+
+pub const DAILY_GUESSES: usize = 6;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// days since the unix epoch, days change at midnight UTC
+pub type Day = u64;
+
+pub fn today() -> Day {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / SECONDS_PER_DAY
+}
+
+// same word for everyone on the same day, without having to keep it anywhere
+pub fn word_of_the_day(day: Day) -> &'static str {
+    let words: Vec<&str> = words().collect();
+    words[(mix(day) % words.len() as u64) as usize]
+}
+
+// splitmix64, so that consecutive days land far apart in the dictionary
+fn mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// yyyy-mm-dd, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub fn date_string(day: Day) -> String {
+    let z = day as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let d = day_of_year - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = year_of_era + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+pub struct DailyEntry {
+    pub started: SystemTime,
+    pub guesses: Vec<Guess>,
+    // none while still guessing
+    pub solved: Option<bool>,
+    finished: Option<SystemTime>,
+}
+
+impl DailyEntry {
+    fn duration(&self) -> Duration {
+        self.finished
+            .unwrap_or_else(SystemTime::now)
+            .duration_since(self.started)
+            .unwrap_or_default()
+    }
+
+    // the hints without the letters, so results can be compared without spoiling the word
+    fn to_grid_string(&self, target: &str) -> String {
+        self.guesses
+            .iter()
+            .map(|guess| {
+                hint(target, &guess.word)
+                    .chars()
+                    .map(|mark| match mark {
+                        '_' => '⬛',
+                        mark if mark.is_uppercase() => '🟩',
+                        _ => '🟨',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn result_string(&self) -> String {
+        match self.solved {
+            Some(true) => format!("SOLVED IN {}/{DAILY_GUESSES}", self.guesses.len()),
+            Some(false) => format!("FAILED X/{DAILY_GUESSES}"),
+            None => format!("GUESSING {}/{DAILY_GUESSES}", self.guesses.len()),
+        }
+    }
+}
+
+pub struct Daily {
+    pub day: Day,
+    entries: HashMap<ClientId, DailyEntry>,
+}
+
+impl Daily {
+    pub fn new() -> Self {
+        Daily {
+            day: today(),
+            entries: HashMap::new(),
+        }
+    }
+
+    // everyone starts over once the day changes
+    pub fn refresh(&mut self) {
+        let day = today();
+        if day != self.day {
+            self.day = day;
+            self.entries.clear();
+        }
+    }
+
+    pub fn word(&self) -> &'static str {
+        word_of_the_day(self.day)
+    }
+
+    pub fn is_finished(&self, id: &ClientId) -> bool {
+        self.entries
+            .get(id)
+            .is_some_and(|entry| entry.solved.is_some())
+    }
+
+    // picks up where the player left off, if they already started today
+    pub fn start(&mut self, id: &ClientId) -> Vec<Guess> {
+        self.entries
+            .entry(*id)
+            .or_insert_with(|| DailyEntry {
+                started: SystemTime::now(),
+                guesses: Vec::new(),
+                solved: None,
+                finished: None,
+            })
+            .guesses
+            .clone()
+    }
+
+    pub fn track(&mut self, id: &ClientId, guesses: &[Guess], solved: Option<bool>) {
+        if let Some(entry) = self.entries.get_mut(id) {
+            entry.guesses = guesses.to_vec();
+            entry.solved = solved;
+            if solved.is_some() {
+                entry.finished = Some(SystemTime::now());
+            }
+        }
+    }
+
+    pub fn result_string(&self, id: &ClientId) -> Option<String> {
+        let entry = self.entries.get(id)?;
+        Some(format!(
+            "DAILY {} {} ({}s)\n{}",
+            date_string(self.day),
+            entry.result_string(),
+            entry.duration().as_secs(),
+            entry.to_grid_string(self.word())
+        ))
+    }
+
+    // solved first, by fewest guesses and then fastest, everyone else after
+    pub fn to_html_string(&self) -> String {
+        let mut entries: Vec<(&ClientId, &DailyEntry)> = self.entries.iter().collect();
+        entries.sort_by_key(|(_, entry)| {
+            let rank = match entry.solved {
+                Some(true) => 0,
+                None => 1,
+                Some(false) => 2,
+            };
+            (rank, entry.guesses.len(), entry.duration())
+        });
+        entries
+            .iter()
+            .map(|(id, entry)| {
+                format!(
+                    "<li><strong>{}</strong> {} <em>({}s)</em><br>{}</li>",
+                    to_hex_str(id),
+                    entry.result_string().to_lowercase(),
+                    entry.duration().as_secs(),
+                    entry.to_grid_string(self.word()).replace('\n', "<br>")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_from_days() {
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(11016), "2000-02-29");
+        assert_eq!(date_string(20744), "2026-10-18");
+    }
+
+    #[test]
+    fn word_of_the_day_is_stable() {
+        assert_eq!(word_of_the_day(20744), word_of_the_day(20744));
+        assert_ne!(word_of_the_day(20744), word_of_the_day(20745));
+    }
+}
@@ -0,0 +1,26 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// common five letter words, one per line
+const WORDS: &str = include_str!("words.txt");
+
+pub fn words() -> impl Iterator<Item = &'static str> {
+    WORDS.lines()
+}
+
+pub fn random_word() -> &'static str {
+    let words: Vec<&str> = words().collect();
+    words[random_number() as usize % words.len()]
+}
+
+// there is no rng in std, but the hasher behind HashMap gets seeded randomly for every instance
+pub fn random_number() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    hasher.finish()
+}
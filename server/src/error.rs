@@ -0,0 +1,44 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+
+use crate::client::ClientId;
+
+pub type ServerResult<T> = Result<T, ServerError>;
+
+#[derive(Debug)]
+pub enum ServerError {
+    // reading from or writing to a connection failed
+    Io(io::Error),
+    // nobody with that id is online
+    NotOnline(ClientId),
+    // the client is online, but its connection broke while sending to it
+    Undelivered(ClientId, io::Error),
+}
+
+impl ServerError {
+    // whoever turned out to be unreachable, and should be treated as gone
+    pub fn unreachable(&self) -> Option<ClientId> {
+        match self {
+            ServerError::Undelivered(id, _) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ServerError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ServerError::Io(error) => write!(f, "{error}"),
+            ServerError::NotOnline(id) => write!(f, "{id:04x} is not online"),
+            ServerError::Undelivered(id, error) => write!(f, "couldn't reach {id:04x}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl From<io::Error> for ServerError {
+    fn from(error: io::Error) -> Self {
+        ServerError::Io(error)
+    }
+}
@@ -0,0 +1,831 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use beef_messages::{BeefMessage, Payload};
+
+use crate::accounts::AccountDatabase;
+use crate::battle::{Battle, BattleDatabase, BattleKind, Battles, PRACTICE_GUESSES};
+use crate::client::{to_hex_str, Client, ClientDatabase, ClientId, Clients, Presence};
+use crate::daily::date_string;
+use crate::dictionary::random_word;
+use crate::generic_stream::GenericStream;
+use crate::history::{HistoryDatabase, Outcome};
+use crate::rematch::{Rematch, RematchDatabase, REMATCH_WINDOW};
+use crate::tournament::{start_pending_matches, TournamentDatabase};
+use crate::Stores;
+
+// longer status messages get cut off
+const MAX_STATUS_LENGTH: usize = 60;
+
+pub enum Command {
+    // a client that passed the login, turned away with false if its id is already online
+    Join(ClientId, GenericStream, Sender<bool>),
+    Message(ClientId, BeefMessage),
+    // anything else that needs the clients or battles, like the web pages or the admin
+    Run(Box<dyn FnOnce(&mut Game) + Send>),
+}
+
+// the only way into the game from other threads, commands are handled in the order they come in
+#[derive(Clone)]
+pub struct GameHandle {
+    sender: Sender<Command>,
+}
+
+impl GameHandle {
+    pub fn new() -> (Self, Receiver<Command>) {
+        let (sender, receiver) = mpsc::channel();
+        (GameHandle { sender }, receiver)
+    }
+
+    pub fn join(&self, id: ClientId, stream: GenericStream) -> bool {
+        let (reply, joined) = mpsc::channel();
+        let _ = self.sender.send(Command::Join(id, stream, reply));
+        joined.recv().unwrap_or(false)
+    }
+
+    pub fn message(&self, id: ClientId, msg: BeefMessage) {
+        let _ = self.sender.send(Command::Message(id, msg));
+    }
+
+    // waits for the game thread to get to it, so never call this from the game thread itself
+    pub fn run<R, F>(&self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Game) -> R + Send + 'static,
+    {
+        let (reply, result) = mpsc::channel();
+        let _ = self.sender.send(Command::Run(Box::new(move |game| {
+            let _ = reply.send(f(game));
+        })));
+        result.recv().expect("game thread stopped")
+    }
+}
+
+// clients and battles belong to the game thread alone, so checking something and acting on it
+// can't be interleaved with another connection. the other stores are still locked one at a time
+pub struct Game {
+    pub clients: Clients,
+    pub battles: Battles,
+    pub stores: Stores,
+    // who's spectating whom, until the watched player's beef is over
+    watchers: HashMap<ClientId, ClientId>,
+    // couldn't be written to, let go of once the current command is done
+    unreachable: Vec<ClientId>,
+}
+
+impl Game {
+    pub fn new(stores: Stores) -> Self {
+        Game {
+            clients: Clients::new(),
+            battles: Battles::new(),
+            stores,
+            watchers: HashMap::new(),
+            unreachable: Vec::new(),
+        }
+    }
+
+    pub fn run(mut self, commands: Receiver<Command>) {
+        for command in commands {
+            match command {
+                Command::Join(id, stream, reply) => {
+                    let is_joined = !self.clients.contains_key(&id);
+                    if is_joined {
+                        self.clients.insert(id, Client::new(stream));
+                    }
+                    let _ = reply.send(is_joined);
+                }
+                Command::Message(id, msg) => self.handle(id, msg),
+                Command::Run(f) => f(&mut self),
+            }
+            self.let_go_of_unreachable();
+        }
+    }
+
+    // a failed delivery is only noted, so whatever else the command does still happens. anyone
+    // not online is skipped, there's nobody to tell
+    pub fn send(&mut self, id: &ClientId, msg: impl AsRef<str>) {
+        if let Some(id) = self
+            .clients
+            .send_to(id, msg.as_ref())
+            .err()
+            .and_then(|error| error.unreachable())
+        {
+            self.unreachable.push(id);
+        }
+    }
+
+    // the same as if they had left, their opponent is told and the battle is over
+    fn let_go_of_unreachable(&mut self) {
+        while let Some(id) = self.unreachable.pop() {
+            let Ok(stream) = self.clients.get_stream(&id) else {
+                continue;
+            };
+            println!("lost connection to {id:04x}");
+            // their connection thread finds the stream closed and stops reading
+            stream.shutdown();
+            self.cleanup(&id);
+        }
+    }
+
+    fn handle(&mut self, client_id: ClientId, msg: BeefMessage) {
+        // whatever was still on its way from a client that's gone already
+        if !self.clients.contains_key(&client_id) {
+            return;
+        }
+        // keeping the connection alive doesn't make a player any less idle, those never get here
+        self.clients.touch(&client_id);
+        let stores = self.stores.clone();
+        match msg {
+            BeefMessage::List => {
+                self.send(
+                    &client_id,
+                    format!("beef: USERS ONLINE:\n{}", self.clients.get_ids()),
+                );
+            }
+            BeefMessage::BattleInit(to_id, target) => {
+                if stores
+                    .accounts
+                    .lock()
+                    .unwrap()
+                    .has_blocked(&to_id, &client_id)
+                {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T BEEF, USER {to_id:x} BLOCKED YOU!"),
+                    );
+                    return;
+                }
+                let Ok(new_battle) = self.start_new_beef(client_id, to_id, target) else {
+                    return;
+                };
+                let guesser = new_battle.id.1;
+                let letters = new_battle.letters();
+                self.battles.add_battle(new_battle);
+                self.clients.update_battle_status(&client_id, &to_id, true);
+                self.send(
+                    &client_id,
+                    format!("beef: STARTING BEEF WITH USER {to_id:x}!"),
+                );
+                self.send(
+                    &to_id,
+                    format!(
+                        "beef: USER {client_id:x} HAS BEEF WITH YOU!\n\
+                    beef: WHAT IS YOUR RESPONSE?!"
+                    ),
+                );
+                self.send(&guesser, format!("beef: THE WORD HAS {letters} LETTERS\n"));
+            }
+            // only battle player can guess
+            BeefMessage::BattleGuess(guess) => {
+                // find battle im in and get ids
+                let Some(mut current_battle) = self.battles.get_current_battle(&client_id) else {
+                    self.send(&client_id, "beef: NO BEEF TO GUESS!");
+                    return;
+                };
+                if !current_battle.can_guess(&client_id) {
+                    self.send(&client_id, "beef: CAN'T SECOND-GUESS YOURSELF!");
+                    return;
+                }
+                let to_id = current_battle.get_opponnent(&client_id);
+                let is_solo = current_battle.is_solo();
+                // in a race the opponent is guessing the same word, so guesses stay secret
+                let is_race = current_battle.can_guess(&to_id) && !is_solo;
+                let is_duel = current_battle.kind == BattleKind::Duel;
+
+                let guess = String::from_utf8_lossy(&guess).to_string();
+
+                let is_correct = current_battle.check_guess(client_id, guess.clone());
+                if let BattleKind::Daily = current_battle.kind {
+                    let solved = match current_battle.guesses_left() {
+                        _ if is_correct => Some(true),
+                        Some(0) => Some(false),
+                        _ => None,
+                    };
+                    let mut daily = stores.daily.lock().unwrap();
+                    daily.track(&client_id, current_battle.guesses(), solved);
+                    if let Some(result) =
+                        daily.result_string(&client_id).filter(|_| solved.is_some())
+                    {
+                        self.send(&client_id, format!("beef: {result}"));
+                    }
+                }
+                if is_correct {
+                    let target = current_battle.target.trim().to_string();
+                    self.clean_current_battle(current_battle, Outcome::Guessed);
+                    if is_race {
+                        self.send(
+                            &to_id,
+                            format!("beef: OPPONENT GUESSED {target} FIRST, BEEF LOST!"),
+                        );
+                    } else if !is_solo {
+                        self.send(&to_id, "beef: GUESS CORRECT, BEEF SQUASHED!");
+                    }
+                    self.send(&client_id, "beef: GUESS CORRECT, BEEF SQUASHED!");
+                    if is_duel {
+                        self.announce_series(client_id, to_id);
+                    }
+                    start_pending_matches(self);
+                } else if current_battle.guesses_left() == Some(0) {
+                    let target = current_battle.target.trim().to_string();
+                    self.clean_current_battle(current_battle, Outcome::OutOfGuesses);
+                    if !is_solo {
+                        self.send(&to_id, "beef: OPPONENT IS OUT OF GUESSES!");
+                    }
+                    self.send(
+                        &client_id,
+                        format!("beef: OUT OF GUESSES, THE WORD WAS {target}!"),
+                    );
+                    start_pending_matches(self);
+                } else {
+                    if is_race {
+                        self.send(&to_id, "beef: OPPONENT GUESSED WRONG");
+                    } else if !is_solo {
+                        self.send(&to_id, format!("beef: WRONG GUESS {guess}"));
+                    }
+                    self.send(
+                        &client_id,
+                        format!("beef: WRONG GUESS! {}", current_battle.hint(&guess)),
+                    );
+                    let watched = match is_race {
+                        true => format!("beef: USER {client_id:x} GUESSED WRONG"),
+                        false => format!(
+                            "beef: USER {client_id:x} GUESSED {} {}",
+                            guess.trim(),
+                            current_battle.hint(&guess)
+                        ),
+                    };
+                    self.tell_watchers(&current_battle, watched);
+                    if let Some(left) = current_battle.guesses_left() {
+                        self.send(&client_id, format!("beef: {left} GUESSES LEFT"));
+                    }
+                    self.battles.update_or_add_battle(current_battle);
+                }
+            }
+            BeefMessage::BattleForfeit => {
+                let Some(current_battle) = self.battles.get_current_battle(&client_id) else {
+                    self.send(&client_id, "beef: NO BEEF TO FORFEIT!");
+                    return;
+                };
+                let to_id = current_battle.get_opponnent(&client_id);
+                let is_solo = current_battle.is_solo();
+                let is_duel = current_battle.kind == BattleKind::Duel;
+                let target = current_battle.target.trim().to_string();
+                // giving up on the daily uses up the go for today
+                if let BattleKind::Daily = current_battle.kind {
+                    stores.daily.lock().unwrap().track(
+                        &client_id,
+                        current_battle.guesses(),
+                        Some(false),
+                    );
+                }
+                self.clean_current_battle(current_battle, Outcome::Forfeited(client_id));
+                if is_solo {
+                    self.send(&client_id, format!("beef: GAVE UP, THE WORD WAS {target}!"));
+                    return;
+                }
+                self.send(&to_id, "beef: OPPONENT FORFEITED!");
+                self.send(&client_id, "beef: BEEF FORFEITED!");
+                if is_duel {
+                    self.announce_series(client_id, to_id);
+                }
+                start_pending_matches(self);
+            }
+            // both sides of a battle can talk to each other
+            BeefMessage::Message(payload) => {
+                // find battle im in and get ids
+                let current_battle = self
+                    .battles
+                    .get_current_battle(&client_id)
+                    .filter(|battle| !battle.is_solo());
+                let Some(current_battle) = current_battle else {
+                    self.send(&client_id, "beef: CAN'T MSG, NO BEEFS WITH OTHERS");
+                    return;
+                };
+                let to_id = current_battle.get_opponnent(&client_id);
+                // blocked mid-beef, the battle goes on but the talking stops
+                if stores
+                    .accounts
+                    .lock()
+                    .unwrap()
+                    .has_blocked(&to_id, &client_id)
+                {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T MSG, USER {to_id:x} BLOCKED YOU!"),
+                    );
+                    return;
+                }
+                let payload = String::from_utf8_lossy(&payload).to_string();
+                self.send(&to_id, format!("{client_id:x}: {payload}"));
+            }
+            BeefMessage::Lobby(payload) => {
+                let payload = String::from_utf8_lossy(&payload).to_string();
+                let blocked_by = stores.accounts.lock().unwrap().blocked_by(&client_id);
+                let clients = &self.clients;
+                // bots wouldn't listen anyway
+                let ids: Vec<ClientId> = clients
+                    .keys()
+                    .filter(|&id| {
+                        !id.eq(&client_id) && !clients.is_bot(id) && !blocked_by.contains(id)
+                    })
+                    .copied()
+                    .collect();
+                for id in ids {
+                    self.send(&id, format!("[lobby] {client_id:x}: {payload}"));
+                }
+            }
+            BeefMessage::Whisper(to_id, payload) => {
+                if to_id.eq(&client_id) {
+                    self.send(&client_id, "beef: CAN'T WHISPER TO YOURSELF");
+                    return;
+                }
+                if stores
+                    .accounts
+                    .lock()
+                    .unwrap()
+                    .has_blocked(&to_id, &client_id)
+                {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T WHISPER, USER {to_id:x} BLOCKED YOU!"),
+                    );
+                    return;
+                }
+                if !self.clients.contains_key(&to_id) {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T WHISPER, USER {to_id:x} IS NOT ONLINE!"),
+                    );
+                    return;
+                }
+                let payload = String::from_utf8_lossy(&payload).to_string();
+                self.send(&to_id, format!("[whisper] {client_id:x}: {payload}"));
+            }
+            BeefMessage::Friend(id) | BeefMessage::Block(id) | BeefMessage::Forget(id) => {
+                let mut accounts = stores.accounts.lock().unwrap();
+                if id.eq(&client_id) {
+                    self.send(&client_id, "beef: CAN'T DO THAT TO YOURSELF");
+                    return;
+                }
+                if !accounts.contains_key(&id) {
+                    self.send(&client_id, format!("beef: NO ACCOUNT {id:x}"));
+                    return;
+                }
+                let Some(account) = accounts.get_mut(&client_id) else {
+                    self.send(&client_id, format!("beef: NO ACCOUNT {client_id:x}"));
+                    return;
+                };
+                let reply = match msg {
+                    BeefMessage::Friend(_) if account.befriend(id) => "IS NOW YOUR FRIEND",
+                    BeefMessage::Friend(_) => "IS ALREADY YOUR FRIEND",
+                    BeefMessage::Block(_) if account.block(id) => "IS NOW BLOCKED",
+                    BeefMessage::Block(_) => "IS ALREADY BLOCKED",
+                    _ if account.forget(&id) => "IS OFF YOUR LISTS",
+                    _ => "WASN'T ON YOUR LISTS",
+                };
+                stores.save_accounts(&accounts);
+                self.send(&client_id, format!("beef: USER {id:x} {reply}"));
+            }
+            BeefMessage::SetStatus(status) => {
+                let status = String::from_utf8_lossy(&status).trim().to_string();
+                let (name, message) = status.split_once(' ').unwrap_or((&status, ""));
+                let Some(presence) = Presence::parse(name) else {
+                    self.send(
+                        &client_id,
+                        format!("beef: NO STATUS {name}, PICK available, away OR dnd"),
+                    );
+                    return;
+                };
+                let message = Some(message.trim())
+                    .filter(|message| !message.is_empty())
+                    .map(|message| message.chars().take(MAX_STATUS_LENGTH).collect());
+                self.clients.set_status(&client_id, presence, message);
+                self.send(
+                    &client_id,
+                    format!("beef: STATUS SET TO {}", presence.name().to_uppercase()),
+                );
+            }
+            BeefMessage::FriendList => {
+                self.send(&client_id, self.friend_list_string(&client_id));
+            }
+            BeefMessage::Spectate(id) => {
+                if id.eq(&client_id) {
+                    self.send(&client_id, "beef: CAN'T SPECTATE YOURSELF");
+                    return;
+                }
+                let Some(battle) = self.battles.get_current_battle(&id) else {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T SPECTATE, USER {id:x} ISN'T BEEFING!"),
+                    );
+                    return;
+                };
+                // neither side has to put up with someone they blocked looking on
+                let blocker = {
+                    let accounts = stores.accounts.lock().unwrap();
+                    [battle.id.0, battle.id.1]
+                        .into_iter()
+                        .find(|player| accounts.has_blocked(player, &client_id))
+                };
+                if let Some(blocker) = blocker {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T SPECTATE, USER {blocker:x} BLOCKED YOU!"),
+                    );
+                    return;
+                }
+                if battle.get_opponnent(&id).eq(&client_id) {
+                    self.send(&client_id, "beef: CAN'T SPECTATE YOUR OWN BEEF");
+                    return;
+                }
+                // everyone gets the same daily word, watching would give it away
+                if let BattleKind::Daily = battle.kind {
+                    self.send(&client_id, "beef: CAN'T SPECTATE THE DAILY!");
+                    return;
+                }
+                self.watchers.insert(client_id, id);
+                self.send(
+                    &client_id,
+                    format!(
+                        "beef: SPECTATING USER {id:x}, THE WORD HAS {} LETTERS",
+                        battle.letters()
+                    ),
+                );
+            }
+            BeefMessage::Rematch(word) => {
+                let Some(opponent) = stores.rematches.lock().unwrap().opponent(&client_id) else {
+                    self.send(&client_id, "beef: NO BEEF TO REMATCH!");
+                    return;
+                };
+                if stores
+                    .accounts
+                    .lock()
+                    .unwrap()
+                    .has_blocked(&opponent, &client_id)
+                {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T REMATCH, USER {opponent:x} BLOCKED YOU!"),
+                    );
+                    return;
+                }
+                if !self.clients.contains_key(&opponent) {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T REMATCH, USER {opponent:x} IS NOT ONLINE!"),
+                    );
+                    return;
+                }
+                if self.battles.exists_by_id(&client_id) {
+                    self.send(&client_id, "beef: CAN'T REMATCH, ALREADY BEEFING!");
+                    return;
+                }
+                if self.battles.exists_by_id(&opponent) {
+                    self.send(
+                        &client_id,
+                        format!("beef: CAN'T REMATCH, USER {opponent:x} BUSY!"),
+                    );
+                    return;
+                }
+                if self.clients.get_presence(&opponent) == Some(Presence::DoNotDisturb) {
+                    self.send(
+                        &client_id,
+                        format!(
+                            "beef: CAN'T REMATCH, USER {opponent:x} DOESN'T WANT TO BE DISTURBED!"
+                        ),
+                    );
+                    return;
+                }
+                let is_bot = self.clients.is_bot(&opponent);
+
+                let rematch = {
+                    let mut rematches = stores.rematches.lock().unwrap();
+                    let rematch = rematches.offer(client_id, word);
+                    // bots take every rematch, with a word of their own when it's their pick
+                    if is_bot && matches!(rematch, Ok(Rematch::Offered { .. })) {
+                        rematches.offer(opponent, random_word().into())
+                    } else {
+                        rematch
+                    }
+                };
+                match rematch {
+                    Err(error) => self.send(&client_id, format!("beef: {error}!")),
+                    Ok(Rematch::Offered { to, picks_word }) => {
+                        let answer = if picks_word { "r<word>" } else { "r" };
+                        self.send(
+                            &client_id,
+                            format!("beef: REMATCH OFFERED TO USER {to:x}, WAITING FOR AN ANSWER"),
+                        );
+                        self.send(
+                            &to,
+                            format!(
+                                "beef: USER {client_id:x} WANTS A REMATCH!\n\
+                            beef: SEND {answer} WITHIN {}s TO ACCEPT",
+                                REMATCH_WINDOW.as_secs()
+                            ),
+                        );
+                    }
+                    Ok(Rematch::Accepted(battle)) => {
+                        let (master, guesser) = battle.id;
+                        let letters = battle.letters();
+                        self.battles.add_battle(battle);
+                        self.clients.update_battle_status(&master, &guesser, true);
+                        let series = stores
+                            .rematches
+                            .lock()
+                            .unwrap()
+                            .to_score_string(master, guesser)
+                            .unwrap_or_default();
+                        self.send(
+                            &master,
+                            format!(
+                                "beef: REMATCH! {series}\n\
+                            beef: USER {guesser:x} IS GUESSING YOUR WORD"
+                            ),
+                        );
+                        self.send(
+                            &guesser,
+                            format!(
+                                "beef: REMATCH! {series}\n\
+                            beef: USER {master:x} PICKED THE WORD\n\
+                            beef: THE WORD HAS {letters} LETTERS\n"
+                            ),
+                        );
+                    }
+                }
+            }
+            BeefMessage::Practice => {
+                if self.battles.exists_by_id(&client_id) {
+                    self.send(&client_id, "beef: CAN'T PRACTICE, ALREADY BEEFING!");
+                    return;
+                }
+                let battle = Battle::practice(client_id, random_word().to_string());
+                let letters = battle.letters();
+                self.battles.add_battle(battle);
+                self.clients
+                    .update_battle_status(&client_id, &client_id, true);
+                self.send(
+                    &client_id,
+                    format!(
+                        "beef: PRACTICE STARTED!\n\
+                    beef: THE WORD HAS {letters} LETTERS\n\
+                    beef: {PRACTICE_GUESSES} GUESSES LEFT"
+                    ),
+                );
+            }
+            BeefMessage::Daily => {
+                if self.battles.exists_by_id(&client_id) {
+                    self.send(&client_id, "beef: CAN'T DO THE DAILY, ALREADY BEEFING!");
+                    return;
+                }
+                let mut daily = stores.daily.lock().unwrap();
+                daily.refresh();
+                if daily.is_finished(&client_id) {
+                    self.send(
+                        &client_id,
+                        format!(
+                            "beef: DAILY DONE, COME BACK TOMORROW!\nbeef: {}",
+                            daily.result_string(&client_id).unwrap_or_default()
+                        ),
+                    );
+                    return;
+                }
+                let guesses = daily.start(&client_id);
+                let battle = Battle::daily(client_id, daily.word().to_string(), guesses);
+                let earlier = battle
+                    .guesses()
+                    .iter()
+                    .map(|guess| {
+                        format!(
+                            "beef: EARLIER GUESS {} {}\n",
+                            guess.word.trim(),
+                            battle.hint(&guess.word)
+                        )
+                    })
+                    .collect::<String>();
+                let letters = battle.letters();
+                let left = battle.guesses_left().unwrap_or_default();
+                self.battles.add_battle(battle);
+                self.clients
+                    .update_battle_status(&client_id, &client_id, true);
+                self.send(
+                    &client_id,
+                    format!(
+                        "beef: DAILY {} STARTED!\n\
+                    {earlier}\
+                    beef: THE WORD HAS {letters} LETTERS\n\
+                    beef: {left} GUESSES LEFT",
+                        date_string(daily.day)
+                    ),
+                );
+            }
+            BeefMessage::Tournaments => {
+                let tournaments = stores.tournaments.lock().unwrap();
+                if tournaments.is_empty() {
+                    self.send(&client_id, "beef: NO TOURNAMENTS YET");
+                    return;
+                }
+                self.send(
+                    &client_id,
+                    format!("beef: TOURNAMENTS:\n{}", tournaments.to_summary_string()),
+                );
+            }
+            BeefMessage::TournamentJoin(id) => {
+                let mut tournaments = stores.tournaments.lock().unwrap();
+                let joined = match tournaments.get_tournament(id) {
+                    Some(tournament) => tournament.join(client_id),
+                    None => Err("NO SUCH TOURNAMENT"),
+                };
+                match joined {
+                    Ok(_) => self.send(
+                        &client_id,
+                        format!(
+                            "beef: REGISTERED FOR TOURNAMENT #{id:x}, WAIT FOR THE FIRST ROUND!"
+                        ),
+                    ),
+                    Err(error) => self.send(
+                        &client_id,
+                        format!("beef: CAN'T JOIN TOURNAMENT #{id:x}, {error}!"),
+                    ),
+                }
+            }
+            BeefMessage::History => {
+                let history = stores.history.lock().unwrap();
+                if history.is_empty() {
+                    self.send(&client_id, "beef: NO BEEFS SQUASHED YET");
+                    return;
+                }
+                self.send(
+                    &client_id,
+                    format!("beef: RECENT BEEFS:\n{}", history.to_summary_string(10)),
+                );
+            }
+            BeefMessage::Disconnect => self.cleanup(&client_id),
+            // answered by the connection itself
+            BeefMessage::Ping | BeefMessage::Pong => {}
+            BeefMessage::NotBeef => {
+                self.send(&client_id, "beef: NOT BEEF COMMAND");
+            }
+        }
+    }
+
+    fn start_new_beef(
+        &mut self,
+        my_id: ClientId,
+        to_id: ClientId,
+        target: Payload,
+    ) -> Result<Battle, ()> {
+        if my_id.eq(&to_id) {
+            self.send(&my_id, "beef: CAN'T BEEF WITH YOURSELF");
+            return Err(());
+        }
+        if self.battles.exists_by_id(&my_id) {
+            self.send(&my_id, "beef: CAN'T BEEF, ALREADY BEEFING!");
+            return Err(());
+        }
+        if self.battles.exists_by_id(&to_id) {
+            self.send(&my_id, format!("beef: CAN'T BEEF, USER {to_id:x} BUSY!"));
+            return Err(());
+        }
+        if !self.clients.contains_key(&to_id) {
+            self.send(
+                &my_id,
+                format!("beef: CAN'T BEEF, USER #{to_id:x} IS NOT ONLINE!"),
+            );
+            return Err(());
+        }
+        if self.clients.get_presence(&to_id) == Some(Presence::DoNotDisturb) {
+            self.send(
+                &my_id,
+                format!("beef: CAN'T BEEF, USER {to_id:x} DOESN'T WANT TO BE DISTURBED!"),
+            );
+            return Err(());
+        }
+
+        // bots can't be told a word, so asking one to pick it swaps the roles
+        if target.eq(b"?") && self.clients.is_bot(&to_id) {
+            return Ok(Battle::new(to_id, my_id, random_word().to_string()));
+        }
+
+        Ok(Battle::new(
+            my_id,
+            to_id,
+            String::from_utf8_lossy(&target).to_string(),
+        ))
+    }
+
+    pub fn clean_current_battle(&mut self, battle: Battle, outcome: Outcome) {
+        self.clients
+            .update_battle_status(&battle.id.0, &battle.id.1, false);
+        self.battles.del_battle(&battle.id);
+        self.stop_watching(&battle);
+        self.record_result(battle, outcome);
+    }
+
+    fn cleanup(&mut self, client_id: &ClientId) {
+        self.clients.remove_user(client_id);
+        self.watchers.remove(client_id);
+        let battle = self.battles.del_battle_by_client(client_id);
+        if let Some(battle) = battle.as_ref() {
+            self.stop_watching(battle);
+        }
+        if let Some(battle) = battle.as_ref().filter(|battle| !battle.is_solo()) {
+            let opponent = battle.get_opponnent(client_id);
+            self.clients.remove_battle_status(&opponent);
+            if self.clients.contains_key(&opponent) {
+                self.send(
+                    &opponent,
+                    format!("beef: USER {client_id:x} LEFT, BEEF OVER!"),
+                );
+            }
+        }
+        if let Some(battle) = battle {
+            self.record_result(battle, Outcome::Disconnected(*client_id));
+        }
+        // anyone waiting on this client for a tournament match gets a walkover
+        start_pending_matches(self);
+    }
+
+    // anyone watching either player, minus whoever either of them blocked since
+    fn tell_watchers(&mut self, battle: &Battle, msg: impl AsRef<str>) {
+        let (a, b) = battle.id;
+        let ids: Vec<ClientId> = {
+            let accounts = self.stores.accounts.lock().unwrap();
+            self.watchers
+                .iter()
+                .filter(|(id, watched)| {
+                    [a, b].contains(watched)
+                        && !accounts.has_blocked(&a, id)
+                        && !accounts.has_blocked(&b, id)
+                })
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        for id in ids {
+            self.send(&id, msg.as_ref());
+        }
+    }
+
+    fn stop_watching(&mut self, battle: &Battle) {
+        self.tell_watchers(
+            battle,
+            format!("beef: BEEF OVER, THE WORD WAS {}", battle.target.trim()),
+        );
+        let (a, b) = battle.id;
+        self.watchers
+            .retain(|_, watched| *watched != a && *watched != b);
+    }
+
+    // online friends first with what they're up to, then everyone offline
+    fn friend_list_string(&self, client_id: &ClientId) -> String {
+        let (friends, blocked) = match self.stores.accounts.lock().unwrap().get(client_id) {
+            Some(account) => (account.friends.clone(), account.blocked.clone()),
+            None => return "beef: NO FRIENDS YET".to_string(),
+        };
+        if friends.is_empty() && blocked.is_empty() {
+            return "beef: NO FRIENDS YET".to_string();
+        }
+        let mut friends: Vec<(ClientId, &str)> = friends
+            .into_iter()
+            .map(|id| match self.clients.get_presence(&id) {
+                Some(presence) => (id, presence.name()),
+                None => (id, "offline"),
+            })
+            .collect();
+        friends.sort_by_key(|(id, status)| (*status == "offline", *id));
+        let mut list = "beef: FRIENDS:".to_string();
+        for (id, status) in friends {
+            list.push_str(&format!("\n{} ({status})", to_hex_str(&id)));
+        }
+        if !blocked.is_empty() {
+            let blocked: Vec<String> = blocked.iter().map(to_hex_str).collect();
+            list.push_str(&format!("\nbeef: BLOCKED: {}", blocked.join(", ")));
+        }
+        list
+    }
+
+    fn announce_series(&mut self, a: ClientId, b: ClientId) {
+        let Some(series) = self.stores.rematches.lock().unwrap().to_score_string(a, b) else {
+            return;
+        };
+        for id in [a, b] {
+            self.send(&id, format!("beef: {series}, SEND r FOR A REMATCH"));
+        }
+    }
+
+    fn record_result(&self, battle: Battle, outcome: Outcome) {
+        if let BattleKind::Tournament(id) = battle.kind {
+            self.stores
+                .tournaments
+                .lock()
+                .unwrap()
+                .report(id, &battle, &outcome);
+        }
+        self.stores
+            .rematches
+            .lock()
+            .unwrap()
+            .finish(&battle, &outcome);
+        self.stores.history.lock().unwrap().record(battle, outcome);
+    }
+}
@@ -1,76 +1,141 @@
 use beef_messages::BeefMessage;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::net::TcpStream;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
 use std::os::unix::net::UnixStream;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-// I first did it using a trait GenericStream: Read + Write + Sync + Send but this seems simpler
-// we defined a wrapper enum and some matches to call underlying functions. The downside is that
-// for every new extension we need to define many functions
-pub enum GenericStream {
-    TcpStream(TcpStream),
-    UnixStream(UnixStream),
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::ServerResult;
+
+// whatever a client can be connected through. a new transport only has to implement this,
+// everything else goes through GenericStream
+pub trait Transport: Read + Write + Send + Sync {
+    fn try_clone(&self) -> std::io::Result<Box<dyn Transport>>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()>;
+    fn shutdown(&self) -> std::io::Result<()>;
+    // something to tell connections apart by, ids of new accounts are made from it
+    fn unique_string(&self) -> String;
+}
+
+impl Transport for TcpStream {
+    fn try_clone(&self) -> std::io::Result<Box<dyn Transport>> {
+        Ok(Box::new(TcpStream::try_clone(self)?))
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+    fn shutdown(&self) -> std::io::Result<()> {
+        TcpStream::shutdown(self, Shutdown::Both)
+    }
+    fn unique_string(&self) -> String {
+        self.peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl Transport for UnixStream {
+    fn try_clone(&self) -> std::io::Result<Box<dyn Transport>> {
+        Ok(Box::new(UnixStream::try_clone(self)?))
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+    fn shutdown(&self) -> std::io::Result<()> {
+        UnixStream::shutdown(self, Shutdown::Both)
+    }
+    // unix streams have no address worth mentioning, so they get "randomized"
+    fn unique_string(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos();
+        format!("{}", nanos)
+    }
+}
+
+// I first did it as an enum with a match for every transport, which meant many new arms for
+// every new one. now the transport is behind a trait and this only adds the beef on top
+pub struct GenericStream {
+    transport: Box<dyn Transport>,
 }
 
 impl Read for GenericStream {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        match self {
-            GenericStream::TcpStream(s) => s.read(buf),
-            GenericStream::UnixStream(s) => s.read(buf),
-        }
+        self.transport.read(buf)
     }
 }
 
 impl Write for GenericStream {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        match self {
-            GenericStream::TcpStream(s) => s.write(buf),
-            GenericStream::UnixStream(s) => s.write(buf),
-        }
+        self.transport.write(buf)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        match self {
-            GenericStream::TcpStream(s) => s.flush(),
-            GenericStream::UnixStream(s) => s.flush(),
-        }
+        self.transport.flush()
     }
 }
 
 impl GenericStream {
-    pub fn send_bytes(&self, bytes: &[u8]) {
-        self.get_clone().write_all(bytes).unwrap()
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        GenericStream {
+            transport: Box::new(transport),
+        }
     }
-    pub fn send_msg_string(&self, msg: String) {
-        self.send_msg(msg.as_str());
+
+    pub fn send_bytes(&self, bytes: &[u8]) -> ServerResult<()> {
+        Ok(self.get_clone()?.write_all(bytes)?)
     }
-    pub fn send_msg(&self, msg: &str) {
-        self.send_bytes(format!("{msg}\r\n").as_bytes());
+    pub fn send_msg_string(&self, msg: String) -> ServerResult<()> {
+        self.send_msg(msg.as_str())
     }
-    pub fn receive_msg(&self) -> Result<BeefMessage, ()> {
-        let mut reader = BufReader::new(self.get_clone());
-        let received: Vec<u8> = reader.fill_buf().unwrap().to_vec();
+    pub fn send_msg(&self, msg: &str) -> ServerResult<()> {
+        self.send_bytes(format!("{msg}\r\n").as_bytes())
+    }
+    pub fn receive_msg(&self) -> std::io::Result<BeefMessage> {
+        let mut reader = BufReader::new(self.get_clone()?);
+        let received: Vec<u8> = reader.fill_buf()?.to_vec();
         reader.consume(received.len());
+        // nothing to read means the other side hung up without saying goodbye
+        if received.is_empty() {
+            return Ok(BeefMessage::Disconnect);
+        }
         Ok(received.into())
     }
 
+    // reads give up after this long, instead of waiting on a peer that might be gone for good
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.transport.set_read_timeout(timeout)
+    }
+
+    // writes to a peer that stopped reading give up too, instead of stalling the sender
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        self.transport.set_write_timeout(timeout)
+    }
+
+    // hangs up on the other side, reads on every clone of the stream come back empty
+    pub fn shutdown(&self) {
+        let _ = self.transport.shutdown();
+    }
+
     pub fn get_unique_string(&self) -> String {
-        match self {
-            GenericStream::TcpStream(s) => s.peer_addr().map(|addr| addr.to_string()).unwrap(),
-            GenericStream::UnixStream(_) => {
-                let nanos = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .subsec_nanos();
-                format!("{}", nanos)
-            }
-        }
+        self.transport.unique_string()
     }
 
-    pub fn get_clone(&self) -> Self {
-        match self {
-            GenericStream::TcpStream(s) => GenericStream::TcpStream(s.try_clone().unwrap()),
-            GenericStream::UnixStream(s) => GenericStream::UnixStream(s.try_clone().unwrap()),
-        }
+    pub fn get_clone(&self) -> std::io::Result<Self> {
+        Ok(GenericStream {
+            transport: self.transport.try_clone()?,
+        })
     }
 }
+
+// what a read that ran into its timeout fails with, depending on the platform
+pub fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use crate::battle::{Battle, BattleKind, Guess};
+use crate::client::{to_hex_str, ClientId};
+use crate::http::escape_html;
+
+// older records are dropped, so the history doesn't grow for as long as the server runs
+const MAX_RECORDS: usize = 100;
+
+pub type History = VecDeque<MatchRecord>;
+
+pub enum Outcome {
+    Guessed,
+    OutOfGuesses,
+    Forfeited(ClientId),
+    Disconnected(ClientId),
+    // the server went down before anyone won
+    CalledOff,
+}
+
+pub struct MatchRecord {
+    pub number: u32,
+    pub master: ClientId,
+    pub player: ClientId,
+    pub target: String,
+    pub guesses: Vec<Guess>,
+    pub outcome: Outcome,
+    pub started: SystemTime,
+    pub duration: Duration,
+}
+
+impl MatchRecord {
+    fn outcome_string(&self) -> String {
+        match self.outcome {
+            Outcome::Guessed => format!("GUESSED IN {}", self.guesses.len()),
+            Outcome::OutOfGuesses => "OUT OF GUESSES".to_string(),
+            Outcome::Forfeited(id) => format!("FORFEITED BY {}", to_hex_str(&id)),
+            Outcome::Disconnected(id) => format!("{} DISCONNECTED", to_hex_str(&id)),
+            Outcome::CalledOff => "CALLED OFF".to_string(),
+        }
+    }
+
+    fn to_summary_string(&self) -> String {
+        format!(
+            "#{} {} vs {}: {}, {} ({}s)",
+            self.number,
+            to_hex_str(&self.master),
+            to_hex_str(&self.player),
+            self.target.trim(),
+            self.outcome_string(),
+            self.duration.as_secs()
+        )
+    }
+
+    fn to_html_string(&self) -> String {
+        format!(
+            "<li><a style=\"color: #ff6666\" href=\"/history/{}\">#{}</a> \
+            <strong>{}</strong> vs. <strong>{}</strong> 🡒 {} <em>({}, {}s)</em></li>",
+            self.number,
+            self.number,
+            to_hex_str(&self.master),
+            to_hex_str(&self.player),
+            escape_html(self.target.trim()),
+            self.outcome_string().to_lowercase(),
+            self.duration.as_secs()
+        )
+    }
+
+    // shows the battle as it was after `step` guesses, with links to step back and forth
+    pub fn to_replay_html_string(&self, step: usize) -> String {
+        let step = step.min(self.guesses.len());
+        let guesses = self.guesses[..step]
+            .iter()
+            .enumerate()
+            .map(|(i, guess)| {
+                let offset = guess
+                    .at
+                    .duration_since(self.started)
+                    .unwrap_or_default()
+                    .as_secs_f32();
+                let word = escape_html(guess.word.trim());
+                if i + 1 == step {
+                    format!("<li><strong>{word}</strong> <em>+{offset:.1}s</em></li>")
+                } else {
+                    format!("<li>{word} <em>+{offset:.1}s</em></li>")
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("");
+        let previous = if step > 0 {
+            format!(
+                "<a style=\"color: #ff6666\" href=\"/history/{}?step={}\">« previous</a>",
+                self.number,
+                step - 1
+            )
+        } else {
+            "« previous".to_string()
+        };
+        let next = if step < self.guesses.len() {
+            format!(
+                "<a style=\"color: #ff6666\" href=\"/history/{}?step={}\">next »</a>",
+                self.number,
+                step + 1
+            )
+        } else {
+            format!("next » <em>({})</em>", self.outcome_string().to_lowercase())
+        };
+        format!(
+            "<div style=\"border: 1px dotted #dddddd; margin: 1em; padding: 1em; max-width: 30em;\">
+            <span><strong>#{}</strong> vs. <strong>#{}</strong></span><br><hr>
+            <span>🡒 <strong>{}</strong></span><br>
+            <span>step {step} of {}</span>
+            <ol>{guesses}</ol>
+            <span>{previous} | {next}</span>
+        </div>",
+            to_hex_str(&self.master),
+            to_hex_str(&self.player),
+            escape_html(self.target.trim()),
+            self.guesses.len()
+        )
+    }
+}
+
+pub trait HistoryDatabase {
+    fn record(&mut self, battle: Battle, outcome: Outcome);
+    fn get_record(&self, number: u32) -> Option<&MatchRecord>;
+    fn to_summary_string(&self, count: usize) -> String;
+    fn to_html_string(&self) -> String;
+}
+
+impl HistoryDatabase for History {
+    fn record(&mut self, battle: Battle, outcome: Outcome) {
+        // only beefs between players count, practice and the daily are not kept
+        if matches!(battle.kind, BattleKind::Practice | BattleKind::Daily) {
+            return;
+        }
+        let number = self.back().map(|record| record.number + 1).unwrap_or(1);
+        let duration = battle.started.elapsed().unwrap_or_default();
+        self.push_back(MatchRecord {
+            number,
+            master: battle.id.0,
+            player: battle.id.1,
+            guesses: battle.guesses().to_vec(),
+            target: battle.target,
+            outcome,
+            started: battle.started,
+            duration,
+        });
+        if self.len() > MAX_RECORDS {
+            self.pop_front();
+        }
+    }
+
+    fn get_record(&self, number: u32) -> Option<&MatchRecord> {
+        self.iter().find(|record| record.number == number)
+    }
+
+    // newest first
+    fn to_summary_string(&self, count: usize) -> String {
+        self.iter()
+            .rev()
+            .take(count)
+            .map(|record| record.to_summary_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn to_html_string(&self) -> String {
+        self.iter()
+            .rev()
+            .map(|record| record.to_html_string())
+            .collect::<Vec<String>>()
+            .join("")
+    }
+}
@@ -1,11 +1,55 @@
-use std::io::Write;
-
-use crate::battle::{BattleDatabase, Battles};
-use crate::client::{ClientDatabase, Clients};
+use crate::battle::BattleDatabase;
+use crate::client::ClientDatabase;
+use crate::daily::{date_string, Daily};
+use crate::error::ServerResult;
 use crate::generic_stream::GenericStream;
+use crate::history::{History, HistoryDatabase, MatchRecord};
+use crate::tournament::{TournamentDatabase, Tournaments};
+use crate::Stores;
+
+// routes a GET request to the matching page, anything unknown is a 404.
+// the game is asked before any store gets locked, it might be waiting on one of them itself
+pub fn serve_http(stream: &GenericStream, path: &str, stores: &Stores) -> ServerResult<()> {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    match path.trim_end_matches('/') {
+        "" => {
+            let (clients, battles) = stores
+                .game
+                .run(|game| (game.clients.to_html_string(), game.battles.to_html_string()));
+            let mut daily = stores.daily.lock().unwrap();
+            // nobody may have played yet today, yesterday's board is still up until then
+            daily.refresh();
+            serve_info_site(stream, &clients, &battles, &daily)
+        }
+        "/history" => serve_history_site(stream, &stores.history.lock().unwrap()),
+        "/users.json" => respond_with(
+            stream,
+            "HTTP/1.1 200 OK",
+            "application/json",
+            stores.game.run(|game| game.clients.to_json_string()),
+        ),
+        "/tournaments" => serve_tournament_site(stream, &stores.tournaments.lock().unwrap()),
+        path => {
+            let history = stores.history.lock().unwrap();
+            let record = path
+                .strip_prefix("/history/")
+                .and_then(|number| number.parse().ok())
+                .and_then(|number| history.get_record(number));
+            match record {
+                Some(record) => serve_replay_site(stream, record, query),
+                None => respond(stream, "HTTP/1.1 404 Not Found", String::new()),
+            }
+        }
+    }
+}
 
 // I would usually use a library to handle this, but format!() is surprisingly capable.
-pub fn serve_info_site(stream: &GenericStream, clients: &Clients, battles: &Battles) {
+fn serve_info_site(
+    stream: &GenericStream,
+    clients: &str,
+    battles: &str,
+    daily: &Daily,
+) -> ServerResult<()> {
     let status_line = "HTTP/1.1 200 OK";
     let contents = format!(
         "<!DOCTYPE html>
@@ -34,19 +78,114 @@ pub fn serve_info_site(stream: &GenericStream, clients: &Clients, battles: &Batt
                     <ul style=\"padding-left: 1.2em;\">{}</ul>
                 </div>
                 <div style=\"flex-grow: 3;\">
-                    <strong>Battles happening:</strong><br>
+                    <strong>Battles happening:</strong>
+                    <a style=\"color: #ff6666\" href=\"/history\">(history)</a>
+                    <a style=\"color: #ff6666\" href=\"/tournaments\">(tournaments)</a><br>
                     {}
                 </div>
+                <div style=\"flex-grow: 1;\">
+                    <strong>Daily {}:</strong><br>
+                    <ul style=\"padding-left: 1.2em;\">{}</ul>
+                </div>
             </div>
         </body>
         </html>
     ",
-        clients.to_html_string(),
-        battles.to_html_string()
+        clients,
+        battles,
+        date_string(daily.day),
+        daily.to_html_string()
     );
+    respond(stream, status_line, contents)
+}
+
+fn serve_history_site(stream: &GenericStream, history: &History) -> ServerResult<()> {
+    respond(
+        stream,
+        "HTTP/1.1 200 OK",
+        page(format!(
+            "<a style=\"color: #ff6666\" href=\"/\">« overview</a><br><br>
+            <strong>Beefs squashed:</strong>
+            <ul style=\"padding-left: 1.2em;\">{}</ul>",
+            history.to_html_string()
+        )),
+    )
+}
+
+fn serve_tournament_site(stream: &GenericStream, tournaments: &Tournaments) -> ServerResult<()> {
+    respond(
+        stream,
+        "HTTP/1.1 200 OK",
+        page(format!(
+            "<a style=\"color: #ff6666\" href=\"/\">« overview</a><br><br>
+            <strong>Tournaments:</strong>
+            {}",
+            tournaments.to_html_string()
+        )),
+    )
+}
+
+fn serve_replay_site(
+    stream: &GenericStream,
+    record: &MatchRecord,
+    query: &str,
+) -> ServerResult<()> {
+    let step = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("step="))
+        .and_then(|step| step.parse().ok())
+        .unwrap_or(0);
+    respond(
+        stream,
+        "HTTP/1.1 200 OK",
+        page(format!(
+            "<a style=\"color: #ff6666\" href=\"/history\">« all beefs</a>
+            {}",
+            record.to_replay_html_string(step)
+        )),
+    )
+}
+
+// plain page without the banner, for everything except the overview
+fn page(body: String) -> String {
+    format!(
+        "<!DOCTYPE html>
+        <html lang=\"en\">
+        <head>
+            <meta charset=\"utf-8\">
+            <title>wguessr</title>
+        </head>
+        <body style=\"background: #333030; color: #dddddd; margin: 1em;\">
+            {body}
+        </body>
+        </html>
+    "
+    )
+}
+
+fn respond(stream: &GenericStream, status_line: &str, contents: String) -> ServerResult<()> {
+    respond_with(stream, status_line, "text/html; charset=utf-8", contents)
+}
+
+fn respond_with(
+    stream: &GenericStream,
+    status_line: &str,
+    content_type: &str,
+    contents: String,
+) -> ServerResult<()> {
     let length = contents.len();
 
-    let response = format!("{status_line}\r\nContent-Length: {length}\r\n\r\n{contents}");
+    let response = format!(
+        "{status_line}\r\nContent-Type: {content_type}\r\nContent-Length: {length}\r\n\r\n{contents}"
+    );
+
+    stream.send_bytes(response.as_bytes())
+}
 
-    stream.get_clone().write_all(response.as_bytes()).unwrap();
+// words and messages come straight from players, so they can't be trusted in markup
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
@@ -0,0 +1,588 @@
+extern crate beef_messages;
+
+use std::io::{self, BufRead, BufReader};
+use std::net::{SocketAddr, TcpListener};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::{fs, thread};
+
+use beef_messages::{BeefError, BeefMessage};
+
+use crate::accounts::{Account, Accounts};
+use crate::bot::{spawn_bot, Difficulty};
+use crate::client::{get_hash, ClientId};
+use crate::config::Config;
+use crate::daily::Daily;
+use crate::error::ServerResult;
+use crate::game::{Game, GameHandle};
+use crate::generic_stream::{is_timeout, GenericStream, Transport};
+use crate::history::History;
+use crate::http::serve_http;
+use crate::limits::ConnectionLimits;
+use crate::pool::WorkerPool;
+use crate::rematch::Rematches;
+use crate::shutdown::{shut_down, ConnectionGuard, Shutdown};
+use crate::tournament::Tournaments;
+
+mod accounts;
+pub mod admin;
+pub mod battle;
+mod bot;
+pub mod client;
+pub mod config;
+mod daily;
+mod dictionary;
+pub mod error;
+pub mod game;
+mod generic_stream;
+mod history;
+mod http;
+mod limits;
+#[cfg(test)]
+mod memory_stream;
+mod pool;
+mod rematch;
+pub mod shutdown;
+mod tournament;
+
+// typing a password takes longer than answering a ping, but nobody gets to sit on a worker forever
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+// global data stores, each one behind its own lock. clients and battles live on the game thread
+#[derive(Clone)]
+pub struct Stores {
+    pub game: GameHandle,
+    pub history: Arc<Mutex<History>>,
+    pub accounts: Arc<Mutex<Accounts>>,
+    pub daily: Arc<Mutex<Daily>>,
+    pub tournaments: Arc<Mutex<Tournaments>>,
+    pub rematches: Arc<Mutex<Rematches>>,
+    pub shutdown: Shutdown,
+    // read once at startup, so no lock needed
+    pub config: Arc<Config>,
+}
+
+impl Stores {
+    // starts the game thread, which lives as long as the server does
+    pub fn new(config: Config) -> Self {
+        let (game, commands) = GameHandle::new();
+        // a file that can't be read is reported, the server starts without the accounts in it
+        let accounts = match &config.accounts_file {
+            Some(path) => accounts::load(path).unwrap_or_else(|error| {
+                println!("{}: {error}, starting without accounts", path.display());
+                Accounts::new()
+            }),
+            None => Accounts::new(),
+        };
+        let stores = Stores {
+            game,
+            history: Arc::new(Mutex::new(History::new())),
+            accounts: Arc::new(Mutex::new(accounts)),
+            daily: Arc::new(Mutex::new(Daily::new())),
+            tournaments: Arc::new(Mutex::new(Tournaments::new())),
+            rematches: Arc::new(Mutex::new(Rematches::new())),
+            shutdown: Shutdown::default(),
+            config: Arc::new(config),
+        };
+        let stores_ref = stores.clone();
+        thread::spawn(move || Game::new(stores_ref).run(commands));
+        stores
+    }
+
+    // called with the accounts still locked, so saves happen in the order the changes did
+    pub fn save_accounts(&self, accounts: &Accounts) {
+        let Some(path) = &self.config.accounts_file else {
+            return;
+        };
+        if let Err(error) = accounts::save(accounts, path) {
+            println!("couldn't save accounts to {}: {error}", path.display());
+        }
+    }
+}
+
+// both listeners and the workers behind them, serving until shut down
+pub struct Server {
+    pub stores: Stores,
+    pub tcp_addr: SocketAddr,
+    pub socket_path: PathBuf,
+    tcp_thread: JoinHandle<()>,
+    unix_thread: JoinHandle<()>,
+}
+
+impl Server {
+    // port 0 picks any free port, tcp_addr tells which one it was
+    pub fn start(config: Config, server_addr: &str, socket_path: &str) -> io::Result<Self> {
+        let stores = Stores::new(config);
+
+        // every connection, beef or http, is handled by one of a fixed number of workers
+        let stores_ref = stores.clone();
+        let pool = Arc::new(WorkerPool::new(
+            stores.config.workers,
+            stores.config.backlog,
+            move |stream: Box<GenericStream>| match handle_connection(stream, stores_ref.clone()) {
+                Ok(_) => {}
+                Err(error) => {
+                    println!("stream closed: {error}")
+                }
+            },
+        ));
+
+        /*
+            using futures we could just turn iters into streams and merge them, without the need
+            for two separate threads and arc::clones for them, but I wanted 0 dependencies declared
+            in toml, even if the dependency is from the rust team itself
+        */
+        let tcp_listener = TcpListener::bind(server_addr)?;
+        let tcp_addr = tcp_listener.local_addr()?;
+        let pool_ref = Arc::clone(&pool);
+        let shutdown = stores.shutdown.clone();
+        let tcp_thread =
+            thread::spawn(move || accept(tcp_listener.incoming(), &pool_ref, &shutdown));
+
+        // handle Unix streams (cleanup if needed)
+        match fs::remove_file(socket_path) {
+            Ok(_) => {}
+            Err(error) => {
+                println!("{}", error)
+            }
+        }
+        let unix_listener = UnixListener::bind(socket_path)?;
+        let shutdown = stores.shutdown.clone();
+        let unix_thread = thread::spawn(move || accept(unix_listener.incoming(), &pool, &shutdown));
+
+        Ok(Server {
+            stores,
+            tcp_addr,
+            socket_path: PathBuf::from(socket_path),
+            tcp_thread,
+            unix_thread,
+        })
+    }
+
+    // built-in bots, so there is always someone to beef with
+    pub fn spawn_bots(&self) -> io::Result<()> {
+        for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+            let (server_end, bot_end) = UnixStream::pair()?;
+            let stream = Box::new(GenericStream::new(server_end));
+            let stores_ref = self.stores.clone();
+            thread::spawn(move || handle_connection(stream, stores_ref));
+            spawn_bot(bot_end, difficulty, self.stores.game.clone());
+        }
+        Ok(())
+    }
+
+    // raises this server's shutdown flag, see shut_down for how players are let go of
+    pub fn shut_down(self) -> io::Result<()> {
+        shut_down(&self.stores, self.tcp_addr, &self.socket_path);
+        let _ = self.tcp_thread.join();
+        let _ = self.unix_thread.join();
+        fs::remove_file(&self.socket_path)
+    }
+}
+
+fn accept<T: Transport + 'static>(
+    incoming: impl Iterator<Item = io::Result<T>>,
+    pool: &WorkerPool<Box<GenericStream>>,
+    shutdown: &Shutdown,
+) {
+    for stream in incoming {
+        if shutdown.is_requested() {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let stream = Box::new(GenericStream::new(stream));
+        if let Err(stream) = pool.try_send(stream) {
+            turn_away(*stream);
+        }
+    }
+}
+
+// no worker free and the backlog full, so there is nobody to talk to this one
+fn turn_away(stream: GenericStream) {
+    let _ = stream.send_msg_string(BeefError::ServerFull.to_string());
+}
+
+// connections only read, keep an eye on the limits and pass commands on to the game
+fn handle_connection(stream: Box<GenericStream>, stores: Stores) -> ServerResult<()> {
+    let _guard = ConnectionGuard::new(&stores.shutdown);
+    stream.set_read_timeout(Some(LOGIN_TIMEOUT))?;
+    // the game thread writes to everyone, one stuck reader mustn't hold it up for long
+    stream.set_write_timeout(Some(stores.config.write_timeout))?;
+    let Some((client_id, client_stream)) = check_client(stream, &stores)? else {
+        return Ok(());
+    };
+    // logged in, from now on going quiet gets a ping
+    client_stream.set_read_timeout(Some(stores.config.heartbeat_interval))?;
+    let served = serve_client(&client_id, &client_stream, &stores);
+    // however it ended, the game has to let go of the client
+    stores.game.message(client_id, BeefMessage::Disconnect);
+    served
+}
+
+fn serve_client(
+    client_id: &ClientId,
+    client_stream: &GenericStream,
+    stores: &Stores,
+) -> ServerResult<()> {
+    let mut limits = ConnectionLimits::default();
+    let mut is_pinged = false;
+    loop {
+        // a broken connection is as good as gone, it gets cleaned up the same way
+        let msg = match client_stream.receive_msg() {
+            Ok(msg) => msg,
+            // quiet for a while, check if anyone's still there before giving up on them
+            Err(error) if is_timeout(&error) && !is_pinged => {
+                is_pinged = true;
+                client_stream.send_msg("beef: PING")?;
+                continue;
+            }
+            Err(_) => BeefMessage::Disconnect,
+        };
+        is_pinged = false;
+        // flooding gets the same treatment as leaving
+        let msg = match limits.check(stores, client_id, &msg) {
+            Ok(_) => msg,
+            Err(error @ BeefError::Flooding) => {
+                client_stream.send_msg_string(error.to_string())?;
+                BeefMessage::Disconnect
+            }
+            Err(error) => {
+                client_stream.send_msg_string(error.to_string())?;
+                continue;
+            }
+        };
+        match msg {
+            BeefMessage::Ping => client_stream.send_msg("beef: PONG")?,
+            BeefMessage::Pong => {}
+            BeefMessage::Disconnect => return Ok(()),
+            msg => stores.game.message(*client_id, msg),
+        }
+    }
+}
+
+// hand the stream over to the game, keeping a clone of it to read from
+fn check_client(
+    stream: Box<GenericStream>,
+    stores: &Stores,
+) -> ServerResult<Option<(ClientId, GenericStream)>> {
+    let mut request = Vec::new();
+    for line in BufReader::new(stream.get_clone()?).lines() {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+        request.push(line);
+    }
+
+    let protocol_identifier = &request.first().cloned().unwrap_or("".to_string());
+    // find out type of request, if beef is the only thing sent, continue execution
+    // otherwise just serve html once or return 404
+    if let Some(path) = protocol_identifier
+        .strip_prefix("GET ")
+        .and_then(|rest| rest.strip_suffix(" HTTP/1.1"))
+    {
+        serve_http(&stream, path, stores)?;
+        return Ok(None);
+    }
+    // "beef <id>" logs back into an existing account, plain "beef" makes a new one
+    let existing_id = match protocol_identifier.split_once(' ') {
+        None if "beef".eq(protocol_identifier) => None,
+        Some(("beef", id)) => match ClientId::from_str_radix(id, 16) {
+            Ok(id) => Some(id),
+            Err(_) => {
+                stream.send_msg("beef: AUTH FAILURE, NOT AN ID")?;
+                return Ok(None);
+            }
+        },
+        _ => {
+            stream.send_bytes(b"HTTP/1.1 404 Not Found\r\n\r\n")?;
+            return Ok(None);
+        }
+    };
+
+    stream.send_msg("beef: PROTOCOL ENGAGED")?;
+
+    if let Some(client_id) = existing_id {
+        if !stores.accounts.lock().unwrap().contains_key(&client_id) {
+            stream.send_msg_string(format!("beef: AUTH FAILURE, NO ACCOUNT {client_id:x}"))?;
+            return Ok(None);
+        }
+        // checked again when joining, this one just saves asking for the password for nothing
+        if stores
+            .game
+            .run(move |game| game.clients.contains_key(&client_id))
+        {
+            stream.send_msg_string(format!("beef: AUTH FAILURE, {client_id:x} ALREADY ONLINE"))?;
+            return Ok(None);
+        }
+        stream.send_msg("beef: AUTH EXISTING, VALIDATE m<password>")?;
+    } else {
+        stream.send_msg("beef: AUTH NEW, SET PASSWORD m<password>:")?;
+    }
+
+    //
+    let Some(password) = stream.receive_msg()?.get_payload() else {
+        return Ok(None);
+    };
+    let client_stream = stream.get_clone()?;
+
+    if let Some(client_id) = existing_id {
+        let is_valid = stores
+            .accounts
+            .lock()
+            .unwrap()
+            .get(&client_id)
+            .is_some_and(|account| account.check_password(&password));
+        if !is_valid {
+            stream.send_msg_string(format!("beef: AUTH FAILURE {client_id:x}"))?;
+            return Ok(None);
+        }
+        if !stores.game.join(client_id, *stream) {
+            client_stream
+                .send_msg_string(format!("beef: AUTH FAILURE, {client_id:x} ALREADY ONLINE"))?;
+            return Ok(None);
+        }
+        // joined already, so a failed send is left to the first read, which tells the game
+        let _ = client_stream.send_msg_string(format!("beef: AUTH SUCCESS {client_id:x}"));
+        return Ok(Some((client_id, client_stream)));
+    }
+
+    // just generate some unique id, unix stream get "randomized", tcp from address.
+    // picked and taken under the same lock, so two new players can't end up with the same one
+    let client_id = {
+        let mut accounts = stores.accounts.lock().unwrap();
+        let mut client_id: ClientId = get_hash(&stream.get_unique_string());
+        while accounts.contains_key(&client_id) {
+            client_id = client_id.wrapping_add(1);
+        }
+        accounts.insert(client_id, Account::new(password));
+        stores.save_accounts(&accounts);
+        client_id
+    };
+    stores.game.join(client_id, *stream);
+
+    // welcome only once registered, so whoever gets welcomed can already be found by others
+    let _ = client_stream.send_msg_string(format!(
+        "beef: WELCOME, {client_id:x}!
+      ENTER l TO LIST OTHER USERS,
+      ENTER d TO DISCONNECT,
+      ENTER h TO SEE RECENT BEEFS,
+      ENTER p TO PRACTICE ON YOUR OWN,
+      ENTER c FOR THE DAILY CHALLENGE,
+      ENTER t TO LIST TOURNAMENTS, j<id> TO JOIN ONE,
+      ENTER b<id><word> TO BEEF WITH USER,
+      ENTER b<id>? TO HAVE A BOT PICK THE WORD,
+      ENTER m<text> TO TALK TO WHOEVER YOU'RE BEEFING WITH,
+      ENTER a<text> TO TALK TO EVERYONE, w<id><text> TO WHISPER TO ONE USER,
+      ENTER r (OR r<word> WHEN IT'S YOUR PICK) FOR A REMATCH,
+      ENTER v<id> TO SPECTATE A USER'S BEEF,
+      ENTER F<id> TO ADD A FRIEND, B<id> TO BLOCK, U<id> TO UNDO EITHER,
+      ENTER L TO LIST YOUR FRIENDS,
+      ENTER s<available|away|dnd> <message> TO SET YOUR STATUS!"
+    ));
+    Ok(Some((client_id, client_stream)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::battle::BattleDatabase;
+    use crate::client::to_hex_str;
+    use crate::history::HistoryDatabase;
+    use crate::memory_stream::{duplex, MemoryStream};
+
+    // a player on the other end of an in-memory connection, the server can't tell the difference
+    struct TestClient {
+        stream: MemoryStream,
+        received: String,
+    }
+
+    impl TestClient {
+        fn connect(stores: &Stores) -> Self {
+            let (server_end, client_end) = duplex();
+            let stores = stores.clone();
+            thread::spawn(move || {
+                handle_connection(Box::new(GenericStream::new(server_end)), stores)
+            });
+            client_end
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            TestClient {
+                stream: client_end,
+                received: String::new(),
+            }
+        }
+
+        fn sign_up(stores: &Stores, password: &str) -> (Self, ClientId) {
+            let mut client = TestClient::connect(stores);
+            client.send(b"beef\r\n\r\n");
+            client.expect("SET PASSWORD");
+            client.send(format!("m{password}").as_bytes());
+            client.expect("WELCOME, ");
+            let id = client.expect("!").trim_end_matches('!').to_string();
+            (client, ClientId::from_str_radix(&id, 16).unwrap())
+        }
+
+        fn send(&mut self, bytes: &[u8]) {
+            self.stream.write_all(bytes).unwrap();
+        }
+
+        // everything up to and including the expected text, which has to show up in time
+        fn expect(&mut self, text: &str) -> String {
+            let mut buf = [0; 1024];
+            while !self.received.contains(text) {
+                match self.stream.read(&mut buf) {
+                    Ok(count) if count > 0 => {
+                        self.received
+                            .push_str(&String::from_utf8_lossy(&buf[..count]));
+                    }
+                    _ => panic!("expected {text:?}, got {:?}", self.received),
+                }
+            }
+            let end = self.received.find(text).unwrap() + text.len();
+            self.received.drain(..end).collect()
+        }
+
+        fn expect_hang_up(&mut self) {
+            let mut buf = [0; 1024];
+            while self.stream.read(&mut buf).unwrap() > 0 {}
+        }
+    }
+
+    fn command(opcode: u8, id: ClientId, payload: &str) -> Vec<u8> {
+        let mut bytes = vec![opcode];
+        bytes.extend(id.to_be_bytes());
+        bytes.extend(payload.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn sign_up_list_and_leave() {
+        let stores = Stores::new(Config::default());
+        let (mut client, id) = TestClient::sign_up(&stores, "pw");
+        client.send(b"l");
+        client.expect(&format!("USERS ONLINE:\n{} (available)", to_hex_str(&id)));
+
+        client.send(b"d");
+        client.expect_hang_up();
+        assert!(!stores.game.run(move |game| game.clients.contains_key(&id)));
+        assert!(stores.accounts.lock().unwrap().contains_key(&id));
+    }
+
+    #[test]
+    fn battle_until_guessed() {
+        let stores = Stores::new(Config::default());
+        let (mut a, a_id) = TestClient::sign_up(&stores, "a");
+        let (mut b, b_id) = TestClient::sign_up(&stores, "b");
+
+        a.send(&command(b'b', b_id, "apple"));
+        a.expect(&format!("STARTING BEEF WITH USER {b_id:x}!"));
+        b.expect(&format!("USER {a_id:x} HAS BEEF WITH YOU!"));
+        b.expect("THE WORD HAS 5 LETTERS");
+        assert!(stores
+            .game
+            .run(move |game| game.battles.exists_by_id(&b_id)));
+
+        b.send(b"gpeach");
+        b.expect("WRONG GUESS! p");
+        a.expect("WRONG GUESS peach");
+        b.send(b"gapple");
+        b.expect("GUESS CORRECT, BEEF SQUASHED!");
+        a.expect("GUESS CORRECT, BEEF SQUASHED!");
+
+        assert!(stores.game.run(|game| game.battles.is_empty()));
+        assert_eq!(
+            stores
+                .history
+                .lock()
+                .unwrap()
+                .to_summary_string(10)
+                .lines()
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn spectating_stops_at_a_block() {
+        let stores = Stores::new(Config::default());
+        let (mut a, a_id) = TestClient::sign_up(&stores, "a");
+        let (mut b, b_id) = TestClient::sign_up(&stores, "b");
+        let (mut c, c_id) = TestClient::sign_up(&stores, "c");
+
+        a.send(&command(b'b', b_id, "apple"));
+        b.expect("THE WORD HAS 5 LETTERS");
+        c.send(&command(b'v', a_id, ""));
+        c.expect(&format!("SPECTATING USER {a_id:x}, THE WORD HAS 5 LETTERS"));
+        b.send(b"gpeach");
+        c.expect(&format!("USER {b_id:x} GUESSED peach pea__"));
+
+        // blocked mid-beef, nothing more gets through
+        a.send(&command(b'B', c_id, ""));
+        a.expect("IS NOW BLOCKED");
+        b.send(b"gplums");
+        b.expect("WRONG GUESS!");
+        b.send(b"gapple");
+        b.expect("GUESS CORRECT, BEEF SQUASHED!");
+        c.send(&command(b'v', a_id, ""));
+        let seen = c.expect(&format!("CAN'T SPECTATE, USER {a_id:x} ISN'T BEEFING!"));
+        assert!(!seen.contains("plums") && !seen.contains("BEEF OVER"));
+
+        // the opponent's block counts too
+        b.send(&command(b'b', a_id, "crane"));
+        a.expect("THE WORD HAS 5 LETTERS");
+        c.send(&command(b'v', b_id, ""));
+        c.expect(&format!("CAN'T SPECTATE, USER {a_id:x} BLOCKED YOU!"));
+        b.send(b"f");
+        b.expect("BEEF FORFEITED!");
+        c.send(&command(b'v', b_id, ""));
+        let seen = c.expect(&format!("CAN'T SPECTATE, USER {b_id:x} ISN'T BEEFING!"));
+        assert!(!seen.contains("BEEF OVER"));
+    }
+
+    #[test]
+    fn slow_password_isnt_a_missed_heartbeat() {
+        let stores = Stores::new(Config {
+            heartbeat_interval: Duration::from_millis(50),
+            ..Config::default()
+        });
+        let mut client = TestClient::connect(&stores);
+        client.send(b"beef\r\n\r\n");
+        client.expect("SET PASSWORD");
+        thread::sleep(Duration::from_millis(300));
+        client.send(b"mslow");
+        client.expect("WELCOME, ");
+        client.expect("beef: PING");
+    }
+
+    #[test]
+    fn log_back_in_once() {
+        let stores = Stores::new(Config::default());
+        let (mut client, id) = TestClient::sign_up(&stores, "secret");
+        client.send(b"d");
+        client.expect_hang_up();
+
+        let log_in = |password: &str| {
+            let mut client = TestClient::connect(&stores);
+            client.send(format!("beef {id:x}\r\n\r\n").as_bytes());
+            client.expect("VALIDATE m<password>");
+            client.send(format!("m{password}").as_bytes());
+            client
+        };
+        log_in("guess").expect(&format!("AUTH FAILURE {id:x}"));
+        let mut client = log_in("secret");
+        client.expect(&format!("AUTH SUCCESS {id:x}"));
+
+        let mut again = TestClient::connect(&stores);
+        again.send(format!("beef {id:x}\r\n\r\n").as_bytes());
+        again.expect(&format!("AUTH FAILURE, {id:x} ALREADY ONLINE"));
+    }
+}
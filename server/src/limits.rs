@@ -0,0 +1,183 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use beef_messages::{BeefError, BeefMessage, Limited};
+
+use crate::client::ClientId;
+use crate::config::{Config, Limit};
+use crate::Stores;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    // refills for the time since the last command, the wait for the next token when there's none
+    fn refill(&mut self, limit: Limit, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            return Ok(());
+        }
+        Err(Duration::try_from_secs_f64((1.0 - self.tokens) / limit.rate).unwrap_or(Duration::MAX))
+    }
+
+    fn spend(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+// one token bucket for each kind of limited command, all of them start out full
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: HashMap<Limited, Bucket>,
+}
+
+impl RateLimiter {
+    // only looks, nothing is spent until every limit the command is under agreed
+    pub fn check(&mut self, limited: Limited, config: &Config) -> Result<(), Duration> {
+        let limit = config.limit(limited);
+        let now = Instant::now();
+        self.buckets
+            .entry(limited)
+            .or_insert(Bucket {
+                tokens: limit.burst,
+                updated: now,
+            })
+            .refill(limit, now)
+    }
+
+    pub fn spend(&mut self, limited: Limited) {
+        if let Some(bucket) = self.buckets.get_mut(&limited) {
+            bucket.spend();
+        }
+    }
+}
+
+pub fn limited_by(msg: &BeefMessage) -> Option<Limited> {
+    match msg {
+        BeefMessage::BattleGuess(_) => Some(Limited::Guesses),
+        BeefMessage::Message(_) | BeefMessage::Lobby(_) | BeefMessage::Whisper(_, _) => {
+            Some(Limited::Chat)
+        }
+        BeefMessage::BattleInit(_, _) | BeefMessage::Rematch(_) => Some(Limited::Challenges),
+        _ => None,
+    }
+}
+
+// the limits of a single connection, on top of the ones kept with the account
+#[derive(Default)]
+pub struct ConnectionLimits {
+    limiter: RateLimiter,
+    violations: VecDeque<Instant>,
+}
+
+impl ConnectionLimits {
+    pub fn check(
+        &mut self,
+        stores: &Stores,
+        client_id: &ClientId,
+        msg: &BeefMessage,
+    ) -> Result<(), BeefError> {
+        let Some(limited) = limited_by(msg) else {
+            return Ok(());
+        };
+        let config = &stores.config;
+        // the account keeps its buckets across reconnects, so logging in again doesn't refill them
+        let mut accounts = stores.accounts.lock().unwrap();
+        let mut account = accounts.get_mut(client_id);
+        let on_connection = self.limiter.check(limited, config);
+        let on_account = match account.as_mut() {
+            Some(account) => account.limiter.check(limited, config),
+            None => Ok(()),
+        };
+        let retry = match (on_connection, on_account) {
+            (Ok(_), Ok(_)) => {
+                self.limiter.spend(limited);
+                if let Some(account) = account {
+                    account.limiter.spend(limited);
+                }
+                return Ok(());
+            }
+            (Err(a), Err(b)) => a.max(b),
+            (Err(retry), _) | (_, Err(retry)) => retry,
+        };
+        drop(accounts);
+
+        let now = Instant::now();
+        self.violations.push_back(now);
+        while self
+            .violations
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > config.violation_window)
+        {
+            self.violations.pop_front();
+        }
+        if self.violations.len() >= config.max_violations {
+            return Err(BeefError::Flooding);
+        }
+        Err(BeefError::SlowDown(limited, retry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::Account;
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limit = Limit {
+            rate: 2.0,
+            burst: 2.0,
+        };
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            tokens: limit.burst,
+            updated: start,
+        };
+        let mut take = |at: Instant| {
+            bucket.refill(limit, at)?;
+            bucket.spend();
+            Ok(())
+        };
+        assert!(take(start).is_ok());
+        assert!(take(start).is_ok());
+        assert_eq!(take(start), Err(Duration::from_millis(500)));
+
+        // half a second buys one more, but never more than the burst
+        assert!(take(start + Duration::from_millis(500)).is_ok());
+        assert!(take(start + Duration::from_millis(500)).is_err());
+        let later = start + Duration::from_secs(60);
+        assert!(take(later).is_ok());
+        assert!(take(later).is_ok());
+        assert!(take(later).is_err());
+    }
+
+    #[test]
+    fn a_full_account_doesnt_drain_the_connection() {
+        let config = Config {
+            guesses: Limit {
+                rate: 0.001,
+                burst: 1.0,
+            },
+            ..Config::default()
+        };
+        let stores = Stores::new(config);
+        stores
+            .accounts
+            .lock()
+            .unwrap()
+            .insert(1, Account::new(b"pw".to_vec()));
+        let guess = BeefMessage::BattleGuess(b"crane".to_vec());
+
+        // the account is used up elsewhere, this connection hasn't guessed yet
+        let mut elsewhere = ConnectionLimits::default();
+        assert!(elsewhere.check(&stores, &1, &guess).is_ok());
+        let mut here = ConnectionLimits::default();
+        assert!(here.check(&stores, &1, &guess).is_err());
+        assert_eq!(here.limiter.buckets[&Limited::Guesses].tokens, 1.0);
+    }
+}
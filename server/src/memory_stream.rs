@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::generic_stream::Transport;
+
+static DUPLEXES: AtomicUsize = AtomicUsize::new(0);
+
+// bytes going one way, readers wait on it until something arrives or the writer is gone
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<PipeState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct PipeState {
+    bytes: VecDeque<u8>,
+    is_closed: bool,
+}
+
+impl Pipe {
+    fn close(&self) {
+        self.state.lock().unwrap().is_closed = true;
+        self.changed.notify_all();
+    }
+}
+
+// one side of a duplex, shared by all of its clones like a socket is
+struct End {
+    incoming: Arc<Pipe>,
+    outgoing: Arc<Pipe>,
+    read_timeout: Mutex<Option<Duration>>,
+    name: String,
+}
+
+// the last clone going away hangs up, same as closing a socket
+impl Drop for End {
+    fn drop(&mut self) {
+        self.incoming.close();
+        self.outgoing.close();
+    }
+}
+
+// a connection that never leaves the process, so the server can be talked to without sockets
+#[derive(Clone)]
+pub struct MemoryStream {
+    end: Arc<End>,
+}
+
+// two connected ends, whatever is written to one can be read from the other
+pub fn duplex() -> (MemoryStream, MemoryStream) {
+    let number = DUPLEXES.fetch_add(1, Ordering::SeqCst);
+    let (there, back) = (Arc::new(Pipe::default()), Arc::new(Pipe::default()));
+    let end = |incoming: &Arc<Pipe>, outgoing: &Arc<Pipe>, side: &str| MemoryStream {
+        end: Arc::new(End {
+            incoming: Arc::clone(incoming),
+            outgoing: Arc::clone(outgoing),
+            read_timeout: Mutex::new(None),
+            name: format!("memory {number} {side}"),
+        }),
+    };
+    (end(&back, &there, "a"), end(&there, &back, "b"))
+}
+
+impl Read for MemoryStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = self
+            .end
+            .read_timeout
+            .lock()
+            .unwrap()
+            .map(|timeout| Instant::now() + timeout);
+        let pipe = &self.end.incoming;
+        let mut state = pipe.state.lock().unwrap();
+        loop {
+            if !state.bytes.is_empty() {
+                let count = buf.len().min(state.bytes.len());
+                for (slot, byte) in buf.iter_mut().zip(state.bytes.drain(..count)) {
+                    *slot = byte;
+                }
+                return Ok(count);
+            }
+            if state.is_closed {
+                return Ok(0);
+            }
+            state = match deadline {
+                None => pipe.changed.wait(state).unwrap(),
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(ErrorKind::WouldBlock.into());
+                    }
+                    pipe.changed.wait_timeout(state, left).unwrap().0
+                }
+            };
+        }
+    }
+}
+
+impl Write for MemoryStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let pipe = &self.end.outgoing;
+        let mut state = pipe.state.lock().unwrap();
+        if state.is_closed {
+            return Err(ErrorKind::BrokenPipe.into());
+        }
+        state.bytes.extend(buf);
+        pipe.changed.notify_all();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for MemoryStream {
+    fn try_clone(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(self.clone()))
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        *self.end.read_timeout.lock().unwrap() = timeout;
+        Ok(())
+    }
+    // writes never wait, there's no buffer to fill up
+    fn set_write_timeout(&self, _: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+    fn shutdown(&self) -> io::Result<()> {
+        self.end.incoming.close();
+        self.end.outgoing.close();
+        Ok(())
+    }
+    fn unique_string(&self) -> String {
+        self.end.name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplex_carries_bytes_both_ways_until_hung_up() {
+        let (mut a, mut b) = duplex();
+        a.write_all(b"beef").unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(b.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"beef");
+
+        b.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        assert_eq!(b.read(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+
+        // a clone keeps the end open, only the last one hangs up
+        let a_clone = a.clone();
+        drop(a);
+        b.write_all(b"still there").unwrap();
+        drop(a_clone);
+        assert_eq!(b.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            b.write_all(b"hello?").unwrap_err().kind(),
+            ErrorKind::BrokenPipe
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// a fixed number of threads taking work off a bounded queue, so a flood of connections can't
+// spawn threads without end. whatever doesn't fit is handed back to the caller
+pub struct WorkerPool<T> {
+    sender: SyncSender<T>,
+}
+
+impl<T: Send + 'static> WorkerPool<T> {
+    pub fn new<F>(workers: usize, backlog: usize, handler: F) -> Self
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        // with no backlog the channel only hands items to a worker that's already waiting
+        let (sender, receiver) = mpsc::sync_channel(backlog);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let handler = Arc::new(handler);
+        for _ in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            let handler = Arc::clone(&handler);
+            thread::spawn(move || work(receiver, handler));
+        }
+        WorkerPool { sender }
+    }
+
+    pub fn try_send(&self, item: T) -> Result<(), T> {
+        self.sender.try_send(item).map_err(|error| match error {
+            TrySendError::Full(item) | TrySendError::Disconnected(item) => item,
+        })
+    }
+}
+
+fn work<T, F: Fn(T)>(receiver: Arc<Mutex<Receiver<T>>>, handler: Arc<F>) {
+    loop {
+        // the lock is only held while waiting, never while handling
+        let item = receiver.lock().unwrap().recv();
+        match item {
+            Ok(item) => handler(item),
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_pool_hands_items_back() {
+        let (started, is_started) = mpsc::channel();
+        let (release, is_released) = mpsc::channel::<()>();
+        let is_released = Mutex::new(is_released);
+        let pool = WorkerPool::new(1, 1, move |item: u32| {
+            started.send(item).unwrap();
+            is_released.lock().unwrap().recv().unwrap();
+        });
+
+        // one item for the only worker, one waiting in the backlog and no room for a third
+        assert_eq!(pool.try_send(1), Ok(()));
+        assert_eq!(is_started.recv(), Ok(1));
+        assert_eq!(pool.try_send(2), Ok(()));
+        assert_eq!(pool.try_send(3), Err(3));
+
+        release.send(()).unwrap();
+        assert_eq!(is_started.recv(), Ok(2));
+        assert_eq!(pool.try_send(3), Ok(()));
+        release.send(()).unwrap();
+        assert_eq!(is_started.recv(), Ok(3));
+        release.send(()).unwrap();
+    }
+}
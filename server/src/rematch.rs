@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use beef_messages::Payload;
+
+use crate::battle::{Battle, BattleKind};
+use crate::client::{to_hex_str, ClientId};
+use crate::history::Outcome;
+
+// how long the other side has to answer a rematch offer
+pub const REMATCH_WINDOW: Duration = Duration::from_secs(60);
+
+// the same two players share one series, no matter who challenged whom
+pub type Rematches = HashMap<(ClientId, ClientId), Series>;
+
+pub struct Series {
+    // wins for each player of the key, in the same order
+    score: (u32, u32),
+    // master and guesser of the last battle
+    last: (ClientId, ClientId),
+    finished: SystemTime,
+    offer: Option<Offer>,
+}
+
+struct Offer {
+    by: ClientId,
+    // only set when it came from whoever picks the word next
+    word: Option<String>,
+    at: SystemTime,
+}
+
+pub enum Rematch {
+    // waiting on `to`, who has to send a word along if `picks_word`
+    Offered { to: ClientId, picks_word: bool },
+    Accepted(Battle),
+}
+
+fn key(a: ClientId, b: ClientId) -> (ClientId, ClientId) {
+    (a.min(b), a.max(b))
+}
+
+fn other(pair: &(ClientId, ClientId), id: &ClientId) -> ClientId {
+    if pair.0 == *id {
+        pair.1
+    } else {
+        pair.0
+    }
+}
+
+impl Offer {
+    fn in_time(&self, now: SystemTime) -> bool {
+        now.duration_since(self.at).unwrap_or_default() < REMATCH_WINDOW
+    }
+}
+
+impl Series {
+    fn to_score_string(&self, pair: &(ClientId, ClientId)) -> String {
+        format!(
+            "SERIES {} {} - {} {}",
+            to_hex_str(&pair.0),
+            self.score.0,
+            self.score.1,
+            to_hex_str(&pair.1)
+        )
+    }
+}
+
+pub trait RematchDatabase {
+    fn finish(&mut self, battle: &Battle, outcome: &Outcome);
+    fn last_opponent(&self, id: &ClientId) -> Option<ClientId>;
+    fn opponent(&self, id: &ClientId) -> Option<ClientId>;
+    fn offer(&mut self, by: ClientId, word: Payload) -> Result<Rematch, &'static str>;
+    fn to_score_string(&self, a: ClientId, b: ClientId) -> Option<String>;
+}
+
+impl RematchDatabase for Rematches {
+    // only beefs between two players make up a series
+    fn finish(&mut self, battle: &Battle, outcome: &Outcome) {
+        if battle.kind != BattleKind::Duel || battle.is_solo() {
+            return;
+        }
+        let (master, player) = battle.id;
+        let winner = match outcome {
+            Outcome::Guessed => player,
+            Outcome::OutOfGuesses => master,
+            Outcome::Forfeited(id) | Outcome::Disconnected(id) => battle.get_opponnent(id),
+            Outcome::CalledOff => return,
+        };
+        let pair = key(master, player);
+        let series = self.entry(pair).or_insert(Series {
+            score: (0, 0),
+            last: battle.id,
+            finished: SystemTime::now(),
+            offer: None,
+        });
+        if winner == pair.0 {
+            series.score.0 += 1;
+        } else {
+            series.score.1 += 1;
+        }
+        series.last = battle.id;
+        series.finished = SystemTime::now();
+        series.offer = None;
+    }
+
+    fn last_opponent(&self, id: &ClientId) -> Option<ClientId> {
+        self.iter()
+            .filter(|(pair, _)| pair.0 == *id || pair.1 == *id)
+            .max_by_key(|(_, series)| series.finished)
+            .map(|(pair, _)| other(pair, id))
+    }
+
+    // answering an open offer beats rematching whoever was beefed last
+    fn opponent(&self, id: &ClientId) -> Option<ClientId> {
+        let now = SystemTime::now();
+        self.iter()
+            .filter(|(pair, _)| pair.0 == *id || pair.1 == *id)
+            .filter_map(|(pair, series)| Some((pair, series.offer.as_ref()?)))
+            .filter(|(_, offer)| offer.by != *id && offer.in_time(now))
+            .max_by_key(|(_, offer)| offer.at)
+            .map(|(pair, _)| other(pair, id))
+            .or_else(|| self.last_opponent(id))
+    }
+
+    // roles swap, so last battle's guesser picks the word this time
+    fn offer(&mut self, by: ClientId, word: Payload) -> Result<Rematch, &'static str> {
+        let opponent = self.opponent(&by).ok_or("NO BEEF TO REMATCH")?;
+        let series = self
+            .get_mut(&key(by, opponent))
+            .ok_or("NO BEEF TO REMATCH")?;
+        let (guesser, master) = series.last;
+        let word = String::from_utf8_lossy(&word).trim().to_string();
+        let word = match (by == master, word.is_empty()) {
+            (true, true) => return Err("IT'S YOUR TURN TO PICK THE WORD, SEND r<word>"),
+            (true, false) => Some(word),
+            (false, _) => None,
+        };
+
+        let now = SystemTime::now();
+        if let Some(offer) = series.offer.take() {
+            if offer.by == opponent && offer.in_time(now) {
+                let target = word.or(offer.word).unwrap_or_default();
+                return Ok(Rematch::Accepted(Battle::new(master, guesser, target)));
+            }
+        }
+        series.offer = Some(Offer { by, word, at: now });
+        Ok(Rematch::Offered {
+            to: opponent,
+            picks_word: opponent == master,
+        })
+    }
+
+    fn to_score_string(&self, a: ClientId, b: ClientId) -> Option<String> {
+        let pair = key(a, b);
+        self.get(&pair).map(|series| series.to_score_string(&pair))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rematch_swaps_roles() {
+        let mut rematches = Rematches::new();
+        rematches.finish(&Battle::new(1, 2, "crane".into()), &Outcome::Guessed);
+
+        // 2 guessed last time, so 1 can offer but not pick the word
+        assert!(matches!(
+            rematches.offer(1, b"ignored".to_vec()),
+            Ok(Rematch::Offered {
+                to: 2,
+                picks_word: true
+            })
+        ));
+        assert!(rematches.offer(2, Vec::new()).is_err());
+        let Ok(Rematch::Accepted(battle)) = rematches.offer(2, b"eerie".to_vec()) else {
+            panic!("rematch wasn't accepted");
+        };
+        assert_eq!(battle.id, (2, 1));
+        assert_eq!(battle.target, "eerie");
+    }
+
+    #[test]
+    fn series_keeps_score() {
+        let mut rematches = Rematches::new();
+        rematches.finish(&Battle::new(1, 2, "crane".into()), &Outcome::Guessed);
+        rematches.finish(&Battle::new(2, 1, "eerie".into()), &Outcome::Forfeited(1));
+        rematches.finish(&Battle::new(1, 2, "abbey".into()), &Outcome::Guessed);
+        assert_eq!(
+            rematches.to_score_string(2, 1).unwrap(),
+            "SERIES 0001 0 - 3 0002"
+        );
+        assert_eq!(rematches.last_opponent(&1), Some(2));
+        assert_eq!(rematches.last_opponent(&3), None);
+    }
+
+    #[test]
+    fn accepting_an_older_offer() {
+        let mut rematches = Rematches::new();
+        rematches.finish(&Battle::new(1, 2, "crane".into()), &Outcome::Guessed);
+        rematches.finish(&Battle::new(2, 3, "eerie".into()), &Outcome::Guessed);
+
+        // 2 beefed 3 last, but 1's offer is the one waiting on an answer
+        assert!(matches!(
+            rematches.offer(1, Vec::new()),
+            Ok(Rematch::Offered { to: 2, .. })
+        ));
+        let Ok(Rematch::Accepted(battle)) = rematches.offer(2, b"abbey".to_vec()) else {
+            panic!("rematch wasn't accepted");
+        };
+        assert_eq!(battle.id, (2, 1));
+        assert_eq!(rematches.opponent(&3), Some(2));
+    }
+}
@@ -0,0 +1,142 @@
+use std::net::{SocketAddr, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::battle::Battle;
+use crate::client::{ClientDatabase, ClientId};
+use crate::history::Outcome;
+use crate::Stores;
+
+// connections that don't hang up by then are left behind
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SIGINT: i32 = 2;
+const SIGTERM: i32 = 15;
+
+// a signal can only flip a global, every server's own flag is checked next to it
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+// no crate for signals either, libc is linked in anyway
+extern "C" {
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    fn _exit(status: i32) -> !;
+}
+
+// only flips the flag, the actual shutting down happens on the main thread
+extern "C" fn on_signal(_: i32) {
+    // asking twice means right now
+    if SIGNALLED.swap(true, Ordering::SeqCst) {
+        unsafe { _exit(130) }
+    }
+}
+
+pub fn install_signal_handlers() {
+    unsafe {
+        signal(SIGINT, on_signal);
+        signal(SIGTERM, on_signal);
+    }
+}
+
+// one per server, so stopping one leaves any other running in the same process alone
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Shutdown {
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    // until ctrl-c, SIGTERM or a request for this server
+    pub fn wait(&self) {
+        while !self.is_requested() && !SIGNALLED.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+// counts a connection for as long as its thread is alive
+pub(crate) struct ConnectionGuard(Shutdown);
+
+impl ConnectionGuard {
+    pub fn new(shutdown: &Shutdown) -> Self {
+        shutdown.connections.fetch_add(1, Ordering::SeqCst);
+        ConnectionGuard(shutdown.clone())
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn shut_down(stores: &Stores, server_addr: SocketAddr, socket_path: &Path) {
+    stores.shutdown.request();
+    // the accept loops check the flag on every new connection, so give them one
+    let _ = TcpStream::connect(server_addr);
+    let _ = UnixStream::connect(socket_path);
+
+    let countdown = stores.config.shutdown_countdown.as_secs();
+    for left in (1..=countdown).rev() {
+        if left == countdown || left % 5 == 0 || left <= 3 {
+            broadcast(stores, &format!("beef: SERVER SHUTTING DOWN IN {left}s!"));
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+
+    // whatever is still going gets called off, nobody wins
+    stores.game.run(|game| {
+        let battles: Vec<Battle> = game.battles.iter().cloned().collect();
+        for battle in battles {
+            // practice and the daily have the same player on both sides
+            let mut ids = vec![battle.id.0, battle.id.1];
+            ids.dedup();
+            for id in ids {
+                game.send(&id, "beef: SERVER SHUTTING DOWN, BEEF CALLED OFF!");
+            }
+            game.clean_current_battle(battle, Outcome::CalledOff);
+        }
+    });
+
+    // hanging up on everyone makes their threads clean up and finish like after a disconnect
+    broadcast(stores, "beef: SERVER SHUT DOWN, BYE!");
+    stores.game.run(|game| {
+        for client in game.clients.values() {
+            client.stream.shutdown();
+        }
+    });
+    let started = Instant::now();
+    while stores.shutdown.connections() > 0 && started.elapsed() < DRAIN_TIMEOUT {
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn broadcast(stores: &Stores, msg: &str) {
+    let msg = msg.to_string();
+    stores.game.run(move |game| {
+        let clients = &game.clients;
+        let ids: Vec<ClientId> = clients
+            .keys()
+            .filter(|id| !clients.is_bot(id))
+            .copied()
+            .collect();
+        for id in ids {
+            game.send(&id, &msg);
+        }
+    });
+}
@@ -0,0 +1,523 @@
+use std::collections::{HashMap, HashSet};
+
+use beef_messages::TournamentId;
+
+use crate::battle::{Battle, BattleDatabase};
+use crate::client::{to_hex_str, ClientDatabase, ClientId};
+use crate::dictionary::random_word;
+use crate::game::Game;
+use crate::history::Outcome;
+use crate::http::escape_html;
+
+pub type Tournaments = Vec<Tournament>;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    /// Losers are out, winners meet in the next round until one is left
+    Elimination,
+    /// Everybody plays everybody once, most wins takes it
+    RoundRobin,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "elimination" | "single" => Some(Format::Elimination),
+            "roundrobin" | "robin" => Some(Format::RoundRobin),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Format::Elimination => "single elimination",
+            Format::RoundRobin => "round robin",
+        }
+    }
+}
+
+pub struct Match {
+    // no second player is a bye, the first one goes through without playing
+    pub players: (ClientId, Option<ClientId>),
+    pub winner: Option<ClientId>,
+    pub is_running: bool,
+}
+
+impl Match {
+    fn new(a: ClientId, b: Option<ClientId>) -> Self {
+        Match {
+            players: (a, b),
+            winner: if b.is_none() { Some(a) } else { None },
+            is_running: false,
+        }
+    }
+
+    fn has_players(&self, (a, b): (ClientId, ClientId)) -> bool {
+        self.players == (a, Some(b)) || self.players == (b, Some(a))
+    }
+
+    fn to_html_string(&self) -> String {
+        let (a, b) = self.players;
+        let b = b.map(|b| to_hex_str(&b)).unwrap_or("bye".to_string());
+        let state = match (self.winner, self.is_running) {
+            (Some(winner), _) => format!("🡒 <strong>{}</strong>", to_hex_str(&winner)),
+            (None, true) => "⚔️".to_string(),
+            (None, false) => "waiting".to_string(),
+        };
+        format!("<li>{} vs. {b} {state}</li>", to_hex_str(&a))
+    }
+}
+
+pub struct Tournament {
+    pub id: TournamentId,
+    pub name: String,
+    pub format: Format,
+    pub players: Vec<ClientId>,
+    // round robin has every round from the start, elimination gets them as winners come in
+    rounds: Vec<Vec<Match>>,
+    current_round: usize,
+    pub winner: Option<ClientId>,
+    is_announced: bool,
+}
+
+impl Tournament {
+    pub fn new(id: TournamentId, name: String, format: Format) -> Self {
+        Tournament {
+            id,
+            name,
+            format,
+            players: Vec::new(),
+            rounds: Vec::new(),
+            current_round: 0,
+            winner: None,
+            is_announced: false,
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        !self.rounds.is_empty()
+    }
+
+    pub fn join(&mut self, id: ClientId) -> Result<(), &str> {
+        if self.is_started() {
+            return Err("ALREADY STARTED");
+        }
+        if self.players.contains(&id) {
+            return Err("ALREADY REGISTERED");
+        }
+        self.players.push(id);
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<(), &str> {
+        if self.is_started() {
+            return Err("already started");
+        }
+        if self.players.len() < 2 {
+            return Err("needs at least two players");
+        }
+        self.rounds = match self.format {
+            Format::Elimination => vec![pair_up(&self.players, &HashSet::new())],
+            Format::RoundRobin => round_robin(&self.players),
+        };
+        self.advance();
+        Ok(())
+    }
+
+    // matches in the current round which still need to be played
+    pub fn pending_matches(&self) -> Vec<(usize, ClientId, ClientId)> {
+        let Some(round) = self.rounds.get(self.current_round) else {
+            return Vec::new();
+        };
+        round
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.winner.is_none() && !m.is_running)
+            .filter_map(|(i, m)| Some((i, m.players.0, m.players.1?)))
+            .collect()
+    }
+
+    pub fn set_running(&mut self, index: usize) {
+        self.rounds[self.current_round][index].is_running = true;
+    }
+
+    pub fn decide(&mut self, index: usize, winner: ClientId) {
+        let game = &mut self.rounds[self.current_round][index];
+        game.winner = Some(winner);
+        game.is_running = false;
+        self.advance();
+    }
+
+    fn report(&mut self, players: (ClientId, ClientId), winner: ClientId) {
+        let Some(index) = self.rounds.get(self.current_round).and_then(|round| {
+            round
+                .iter()
+                .position(|m| m.is_running && m.has_players(players))
+        }) else {
+            return;
+        };
+        self.decide(index, winner);
+    }
+
+    // moves on once every match in the current round has a winner
+    fn advance(&mut self) {
+        while self.winner.is_none()
+            && self.rounds[self.current_round]
+                .iter()
+                .all(|m| m.winner.is_some())
+        {
+            match self.format {
+                Format::Elimination => {
+                    let winners: Vec<ClientId> = self.rounds[self.current_round]
+                        .iter()
+                        .filter_map(|m| m.winner)
+                        .collect();
+                    if winners.len() == 1 {
+                        self.winner = winners.first().copied();
+                    } else {
+                        self.rounds.push(pair_up(&winners, &self.byes()));
+                        self.current_round += 1;
+                    }
+                }
+                Format::RoundRobin => {
+                    if self.current_round + 1 < self.rounds.len() {
+                        self.current_round += 1;
+                    } else {
+                        self.winner = self.standings().first().map(|(id, _)| *id);
+                    }
+                }
+            }
+        }
+    }
+
+    // whoever sat a round out so far
+    fn byes(&self) -> HashSet<ClientId> {
+        self.rounds
+            .iter()
+            .flatten()
+            .filter(|m| m.players.1.is_none())
+            .map(|m| m.players.0)
+            .collect()
+    }
+
+    // wins per player, ties are broken by who registered first
+    fn standings(&self) -> Vec<(ClientId, usize)> {
+        let mut wins: HashMap<ClientId, usize> = HashMap::new();
+        for m in self.rounds.iter().flatten() {
+            if let (Some(winner), Some(_)) = (m.winner, m.players.1) {
+                *wins.entry(winner).or_default() += 1;
+            }
+        }
+        let mut standings: Vec<(ClientId, usize)> = self
+            .players
+            .iter()
+            .map(|id| (*id, wins.get(id).copied().unwrap_or(0)))
+            .collect();
+        standings.sort_by_key(|(_, wins)| std::cmp::Reverse(*wins));
+        standings
+    }
+
+    fn state_string(&self) -> String {
+        match (self.winner, self.is_started()) {
+            (Some(winner), _) => format!("WON BY {}", to_hex_str(&winner)),
+            (None, true) => format!("ROUND {}", self.current_round + 1),
+            (None, false) => "REGISTERING".to_string(),
+        }
+    }
+
+    fn to_summary_string(&self) -> String {
+        format!(
+            "#{:x} {} ({}, {} PLAYERS, {})",
+            self.id,
+            self.name,
+            self.format.name().to_uppercase(),
+            self.players.len(),
+            self.state_string()
+        )
+    }
+
+    fn to_html_string(&self) -> String {
+        let rounds = self
+            .rounds
+            .iter()
+            .enumerate()
+            .map(|(i, round)| {
+                format!(
+                    "<div style=\"flex-grow: 1;\"><strong>round {}</strong><ul style=\"padding-left: 1.2em;\">{}</ul></div>",
+                    i + 1,
+                    round
+                        .iter()
+                        .map(|m| m.to_html_string())
+                        .collect::<Vec<String>>()
+                        .join("")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("");
+        let standings = match self.format {
+            Format::RoundRobin if self.is_started() => format!(
+                "<span>standings: {}</span>",
+                self.standings()
+                    .iter()
+                    .map(|(id, wins)| format!("{} ({wins})", to_hex_str(id)))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            _ => String::new(),
+        };
+        let players = self
+            .players
+            .iter()
+            .map(to_hex_str)
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(
+            "<div style=\"border: 1px dotted #dddddd; margin: 1em; padding: 1em;\">
+            <span><strong>#{:x} {}</strong> <em>({}, {})</em></span><br><hr>
+            <span>players: {players}</span><br>
+            {standings}
+            <div style=\"display: flex;\">{rounds}</div>
+        </div>",
+            self.id,
+            escape_html(&self.name),
+            self.format.name(),
+            self.state_string().to_lowercase()
+        )
+    }
+}
+
+// pairs players up in the order given. an odd one out gets a bye, the last one who hasn't had one
+// yet, so winners of a bye don't keep ending up at the back and skipping rounds
+fn pair_up(players: &[ClientId], byes: &HashSet<ClientId>) -> Vec<Match> {
+    let mut players = players.to_vec();
+    if players.len() % 2 == 1 {
+        if let Some(index) = players.iter().rposition(|id| !byes.contains(id)) {
+            let odd_one_out = players.remove(index);
+            players.push(odd_one_out);
+        }
+    }
+    players
+        .chunks(2)
+        .map(|pair| Match::new(pair[0], pair.get(1).copied()))
+        .collect()
+}
+
+// circle method: the first player stays put while everyone else rotates around them
+fn round_robin(players: &[ClientId]) -> Vec<Vec<Match>> {
+    let mut seats: Vec<Option<ClientId>> = players.iter().map(|id| Some(*id)).collect();
+    if seats.len() % 2 == 1 {
+        seats.push(None);
+    }
+    let half = seats.len() / 2;
+    (0..seats.len() - 1)
+        .map(|_| {
+            let round = (0..half)
+                .filter_map(|i| match (seats[i], seats[seats.len() - 1 - i]) {
+                    (Some(a), b) => Some(Match::new(a, b)),
+                    (None, Some(b)) => Some(Match::new(b, None)),
+                    (None, None) => None,
+                })
+                .collect();
+            seats[1..].rotate_right(1);
+            round
+        })
+        .collect()
+}
+
+pub trait TournamentDatabase {
+    fn create(&mut self, name: String, format: Format) -> TournamentId;
+    fn get_tournament(&mut self, id: TournamentId) -> Option<&mut Tournament>;
+    fn report(&mut self, id: TournamentId, battle: &Battle, outcome: &Outcome);
+    fn to_summary_string(&self) -> String;
+    fn to_html_string(&self) -> String;
+}
+
+impl TournamentDatabase for Tournaments {
+    fn create(&mut self, name: String, format: Format) -> TournamentId {
+        let id = self.len() as TournamentId + 1;
+        self.push(Tournament::new(id, name, format));
+        id
+    }
+
+    fn get_tournament(&mut self, id: TournamentId) -> Option<&mut Tournament> {
+        self.iter_mut().find(|tournament| tournament.id == id)
+    }
+
+    fn report(&mut self, id: TournamentId, battle: &Battle, outcome: &Outcome) {
+        let winner = match outcome {
+            Outcome::Guessed => battle
+                .guesses()
+                .last()
+                .map(|guess| guess.by)
+                .unwrap_or(battle.id.0),
+            Outcome::OutOfGuesses => battle.id.0,
+            Outcome::Forfeited(loser) | Outcome::Disconnected(loser) => battle.get_opponnent(loser),
+            Outcome::CalledOff => return,
+        };
+        if let Some(tournament) = self.get_tournament(id) {
+            tournament.report(battle.id, winner);
+        }
+    }
+
+    fn to_summary_string(&self) -> String {
+        self.iter()
+            .map(|tournament| tournament.to_summary_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn to_html_string(&self) -> String {
+        self.iter()
+            .rev()
+            .map(|tournament| tournament.to_html_string())
+            .collect::<Vec<String>>()
+            .join("")
+    }
+}
+
+// starts every match whose players are both free, anyone who isn't online loses theirs.
+// has to be called whenever a battle ends, so players waiting on each other get going
+pub fn start_pending_matches(game: &mut Game) {
+    // sent once the tournaments are let go of, with everything already in place
+    let mut outgoing: Vec<(ClientId, String)> = Vec::new();
+    let Game {
+        clients,
+        battles,
+        stores,
+        ..
+    } = game;
+    let mut tournaments = stores.tournaments.lock().unwrap();
+    for tournament in tournaments.iter_mut() {
+        // walkovers can finish a round, which brings up the next one
+        let mut is_changed = true;
+        while is_changed {
+            is_changed = false;
+            for (index, a, b) in tournament.pending_matches() {
+                match (clients.contains_key(&a), clients.contains_key(&b)) {
+                    (true, true) => {
+                        if battles.exists_by_id(&a) || battles.exists_by_id(&b) {
+                            continue;
+                        }
+                        let battle =
+                            Battle::tournament(a, b, random_word().to_string(), tournament.id);
+                        let letters = battle.letters();
+                        battles.add_battle(battle);
+                        clients.update_battle_status(&a, &b, true);
+                        tournament.set_running(index);
+                        for (me, opponent) in [(a, b), (b, a)] {
+                            outgoing.push((
+                                me,
+                                format!(
+                                "beef: TOURNAMENT #{:x} ROUND {}: BEEF WITH USER {opponent:x}!\n\
+                                beef: FIRST ONE TO GUESS THE WORD WINS\n\
+                                beef: THE WORD HAS {letters} LETTERS",
+                                tournament.id,
+                                    tournament.current_round + 1
+                                ),
+                            ));
+                        }
+                    }
+                    (false, true) => {
+                        tournament.decide(index, b);
+                        is_changed = true;
+                    }
+                    _ => {
+                        tournament.decide(index, a);
+                        is_changed = true;
+                    }
+                }
+                if is_changed {
+                    break;
+                }
+            }
+        }
+
+        if let (Some(winner), false) = (tournament.winner, tournament.is_announced) {
+            tournament.is_announced = true;
+            for player in tournament
+                .players
+                .iter()
+                .filter(|id| clients.contains_key(id))
+            {
+                outgoing.push((
+                    *player,
+                    format!("beef: TOURNAMENT #{:x} WON BY {winner:x}!", tournament.id),
+                ));
+            }
+        }
+    }
+    drop(tournaments);
+    for (id, msg) in outgoing {
+        game.send(&id, msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_out(tournament: &mut Tournament) {
+        while tournament.winner.is_none() {
+            let (index, a, b) = tournament.pending_matches()[0];
+            tournament.decide(index, a.min(b));
+        }
+    }
+
+    #[test]
+    fn elimination_gives_byes_and_finds_winner() {
+        let mut tournament = Tournament::new(1, "weekly".to_string(), Format::Elimination);
+        for id in [5, 3, 4, 1, 2] {
+            tournament.join(id).unwrap();
+        }
+        tournament.start().unwrap();
+        assert_eq!(tournament.pending_matches().len(), 2);
+        play_out(&mut tournament);
+        assert_eq!(tournament.winner, Some(1));
+        assert_eq!(tournament.rounds.len(), 3);
+    }
+
+    #[test]
+    fn elimination_byes_go_round() {
+        let mut tournament = Tournament::new(1, "weekly".to_string(), Format::Elimination);
+        for id in [1, 2, 3, 4, 5] {
+            tournament.join(id).unwrap();
+        }
+        tournament.start().unwrap();
+        play_out(&mut tournament);
+
+        // 5 had the first bye, so 3 sits out the second round and 5 has to play
+        let byes: Vec<ClientId> = tournament
+            .rounds
+            .iter()
+            .flatten()
+            .filter(|m| m.players.1.is_none())
+            .map(|m| m.players.0)
+            .collect();
+        assert_eq!(byes, [5, 3]);
+        assert_eq!(tournament.rounds.len(), 3);
+        assert_eq!(tournament.winner, Some(1));
+    }
+
+    #[test]
+    fn round_robin_pairs_everyone_once() {
+        let players = [1, 2, 3, 4, 5];
+        let rounds = round_robin(&players);
+        assert_eq!(rounds.len(), 5);
+        let mut pairs: Vec<(ClientId, ClientId)> = rounds
+            .iter()
+            .flatten()
+            .filter_map(|m| Some((m.players.0.min(m.players.1?), m.players.0.max(m.players.1?))))
+            .collect();
+        pairs.sort();
+        pairs.dedup();
+        assert_eq!(pairs.len(), 10);
+
+        let mut tournament = Tournament::new(1, "weekly".to_string(), Format::RoundRobin);
+        for id in players {
+            tournament.join(id).unwrap();
+        }
+        tournament.start().unwrap();
+        play_out(&mut tournament);
+        assert_eq!(tournament.winner, Some(1));
+    }
+}
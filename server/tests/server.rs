@@ -0,0 +1,372 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier};
+use std::time::Duration;
+use std::{env, fs, process, thread};
+
+use wguessr_server::battle::{hint, BattleDatabase};
+use wguessr_server::client::ClientId;
+use wguessr_server::config::Config;
+use wguessr_server::Server;
+
+static SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+fn socket_path() -> PathBuf {
+    let number = SERVERS.fetch_add(1, Ordering::SeqCst);
+    env::temp_dir().join(format!("wguessr-test-{}-{number}.socket", process::id()))
+}
+
+// a server of its own for every test, on a free port and a socket nobody else uses
+struct TestServer {
+    server: Server,
+}
+
+impl TestServer {
+    fn start() -> Self {
+        let path = socket_path();
+        let server = Server::start(Config::default(), "127.0.0.1:0", path.to_str().unwrap())
+            .expect("server didn't start");
+        TestServer { server }
+    }
+
+    fn battles(&self) -> usize {
+        self.server.stores.game.run(|game| game.battles.len())
+    }
+
+    fn is_online(&self, id: ClientId) -> bool {
+        self.server
+            .stores
+            .game
+            .run(move |game| game.clients.contains_key(&id))
+    }
+
+    fn is_battling(&self, id: ClientId) -> bool {
+        self.server
+            .stores
+            .game
+            .run(move |game| game.battles.exists_by_id(&id))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.server.socket_path);
+    }
+}
+
+// a scripted client, every reply is read line by line and has to be exactly what's expected
+struct Player {
+    id: ClientId,
+    reader: BufReader<Box<dyn Read + Send>>,
+    writer: Box<dyn Write + Send>,
+}
+
+impl Player {
+    fn tcp(server: &TestServer) -> Self {
+        Player::tcp_to(server.server.tcp_addr)
+    }
+
+    fn tcp_to(addr: SocketAddr) -> Self {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        Player {
+            id: 0,
+            reader: BufReader::new(Box::new(stream.try_clone().unwrap())),
+            writer: Box::new(stream),
+        }
+    }
+
+    fn unix(server: &TestServer) -> Self {
+        let stream = UnixStream::connect(&server.server.socket_path).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        Player {
+            id: 0,
+            reader: BufReader::new(Box::new(stream.try_clone().unwrap())),
+            writer: Box::new(stream),
+        }
+    }
+
+    fn sign_up(mut self) -> Self {
+        self.send(b"beef\r\n\r\n");
+        self.expect("beef: PROTOCOL ENGAGED");
+        self.expect("beef: AUTH NEW, SET PASSWORD m<password>:");
+        self.send(b"mpassword");
+        let welcome = self.line();
+        let id = welcome
+            .strip_prefix("beef: WELCOME, ")
+            .and_then(|rest| rest.strip_suffix('!'))
+            .unwrap_or_else(|| panic!("no welcome in {welcome:?}"));
+        self.id = ClientId::from_str_radix(id, 16).unwrap();
+        while !self.line().ends_with("TO SET YOUR STATUS!") {}
+        self
+    }
+
+    fn send(&mut self, bytes: &[u8]) {
+        self.writer.write_all(bytes).unwrap();
+    }
+
+    fn command(&mut self, opcode: u8, to: ClientId, payload: &str) {
+        let mut bytes = vec![opcode];
+        bytes.extend(to.to_be_bytes());
+        bytes.extend(payload.as_bytes());
+        self.send(&bytes);
+    }
+
+    // the next line with something on it
+    fn line(&mut self) -> String {
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return String::new(),
+                Ok(_) => {}
+                Err(error) => panic!("{:04x} got nothing: {error}", self.id),
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            if !line.is_empty() {
+                return line.to_string();
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: &str) {
+        assert_eq!(self.line(), expected, "reply to {:04x}", self.id);
+    }
+
+    fn expect_hang_up(&mut self) {
+        assert_eq!(self.line(), "");
+    }
+}
+
+// the series is always shown with the lower id first
+fn series(a: &Player, a_wins: u8, b: &Player, b_wins: u8) -> String {
+    let line = if a.id < b.id {
+        format!("SERIES {:04x} {a_wins} - {b_wins} {:04x}", a.id, b.id)
+    } else {
+        format!("SERIES {:04x} {b_wins} - {a_wins} {:04x}", b.id, a.id)
+    };
+    format!("beef: {line}, SEND r FOR A REMATCH")
+}
+
+// a has picked apple, b is guessing
+fn start_beef(a: &mut Player, b: &mut Player) {
+    a.command(b'b', b.id, "apple");
+    a.expect(&format!("beef: STARTING BEEF WITH USER {:x}!", b.id));
+    b.expect(&format!("beef: USER {:x} HAS BEEF WITH YOU!", a.id));
+    b.expect("beef: WHAT IS YOUR RESPONSE?!");
+    b.expect("beef: THE WORD HAS 5 LETTERS");
+}
+
+#[test]
+fn list_shows_everyone_online() {
+    let server = TestServer::start();
+    let mut a = Player::tcp(&server).sign_up();
+    let b = Player::unix(&server).sign_up();
+
+    a.send(b"l");
+    a.expect("beef: USERS ONLINE:");
+    let mut listed = vec![a.line(), a.line()];
+    listed.sort();
+    let mut expected = vec![
+        format!("{:04x} (available)", a.id),
+        format!("{:04x} (available)", b.id),
+    ];
+    expected.sort();
+    assert_eq!(listed, expected);
+}
+
+#[test]
+fn battle_guessed_after_a_wrong_guess() {
+    let server = TestServer::start();
+    let mut a = Player::tcp(&server).sign_up();
+    let mut b = Player::tcp(&server).sign_up();
+    start_beef(&mut a, &mut b);
+    assert!(server.is_battling(a.id) && server.is_battling(b.id));
+
+    b.send(b"gpeach");
+    b.expect(&format!("beef: WRONG GUESS! {}", hint("apple", "peach")));
+    a.expect("beef: WRONG GUESS peach");
+
+    b.send(b"gapple");
+    a.expect("beef: GUESS CORRECT, BEEF SQUASHED!");
+    a.expect(&series(&a, 0, &b, 1));
+    b.expect("beef: GUESS CORRECT, BEEF SQUASHED!");
+    b.expect(&series(&a, 0, &b, 1));
+
+    assert_eq!(server.battles(), 0);
+    assert!(server.is_online(a.id) && server.is_online(b.id));
+}
+
+#[test]
+fn forfeit_gives_the_opponent_the_win() {
+    let server = TestServer::start();
+    let mut a = Player::tcp(&server).sign_up();
+    let mut b = Player::tcp(&server).sign_up();
+    start_beef(&mut a, &mut b);
+
+    b.send(b"f");
+    a.expect("beef: OPPONENT FORFEITED!");
+    a.expect(&series(&a, 1, &b, 0));
+    b.expect("beef: BEEF FORFEITED!");
+    b.expect(&series(&a, 1, &b, 0));
+
+    b.send(b"f");
+    b.expect("beef: NO BEEF TO FORFEIT!");
+    assert_eq!(server.battles(), 0);
+}
+
+#[test]
+fn disconnect_mid_battle_tells_the_opponent() {
+    let server = TestServer::start();
+    let mut a = Player::tcp(&server).sign_up();
+    let mut b = Player::tcp(&server).sign_up();
+    start_beef(&mut a, &mut b);
+
+    b.send(b"d");
+    b.expect_hang_up();
+    a.expect(&format!("beef: USER {:x} LEFT, BEEF OVER!", b.id));
+
+    assert!(!server.is_online(b.id));
+    assert!(server.is_online(a.id));
+    assert_eq!(server.battles(), 0);
+    a.send(b"l");
+    a.expect("beef: USERS ONLINE:");
+    a.expect(&format!("{:04x} (available)", a.id));
+}
+
+#[test]
+fn challenging_each_other_at_once_starts_one_battle() {
+    let server = TestServer::start();
+    let a = Player::tcp(&server).sign_up();
+    let b = Player::tcp(&server).sign_up();
+    let (a_id, b_id) = (a.id, b.id);
+
+    let barrier = Arc::new(Barrier::new(2));
+    let challenge = |mut player: Player, to: ClientId| {
+        let barrier = Arc::clone(&barrier);
+        thread::spawn(move || {
+            barrier.wait();
+            player.command(b'b', to, "apple");
+            // the other challenge might get there first, then it's being challenged instead
+            loop {
+                let line = player.line();
+                if line.starts_with("beef: STARTING BEEF") {
+                    return (player, true);
+                }
+                if line == "beef: CAN'T BEEF, ALREADY BEEFING!" {
+                    return (player, false);
+                }
+            }
+        })
+    };
+    let a = challenge(a, b_id);
+    let b = challenge(b, a_id);
+    // the players are kept around, hanging up would end the battle
+    let (_a, a_started) = a.join().unwrap();
+    let (_b, b_started) = b.join().unwrap();
+
+    assert!(a_started != b_started);
+    assert_eq!(server.battles(), 1);
+    assert!(server.is_battling(a_id) && server.is_battling(b_id));
+}
+
+#[test]
+fn only_one_of_many_challengers_gets_the_beef() {
+    let server = TestServer::start();
+    let target = Player::tcp(&server).sign_up();
+    let target_id = target.id;
+    let challengers: Vec<Player> = (0..5).map(|_| Player::tcp(&server).sign_up()).collect();
+
+    let barrier = Arc::new(Barrier::new(challengers.len()));
+    let handles: Vec<_> = challengers
+        .into_iter()
+        .map(|mut player| {
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                player.command(b'b', target_id, "apple");
+                let reply = player.line();
+                (player, reply)
+            })
+        })
+        .collect();
+    let (_players, replies): (Vec<Player>, Vec<String>) = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .unzip();
+
+    let busy = format!("beef: CAN'T BEEF, USER {target_id:x} BUSY!");
+    assert_eq!(replies.iter().filter(|reply| **reply == busy).count(), 4);
+    assert!(replies
+        .iter()
+        .any(|reply| *reply == format!("beef: STARTING BEEF WITH USER {target_id:x}!")));
+    assert_eq!(server.battles(), 1);
+    assert!(server.is_battling(target_id));
+}
+
+#[test]
+fn logging_back_in_and_the_web_page() {
+    let server = TestServer::start();
+    let mut a = Player::tcp(&server).sign_up();
+    let id = a.id;
+    a.send(b"d");
+    a.expect_hang_up();
+
+    let mut back = Player::unix(&server);
+    back.send(format!("beef {id:x}\r\n\r\n").as_bytes());
+    back.expect("beef: PROTOCOL ENGAGED");
+    back.expect("beef: AUTH EXISTING, VALIDATE m<password>");
+    back.send(b"mpassword");
+    back.expect(&format!("beef: AUTH SUCCESS {id:x}"));
+    assert!(server.is_online(id));
+
+    let mut page = Player::tcp(&server);
+    page.send(b"GET /users.json HTTP/1.1\r\n\r\n");
+    page.expect("HTTP/1.1 200 OK");
+    page.expect("Content-Type: application/json");
+    let _length = page.line();
+    assert_eq!(
+        page.line(),
+        format!(
+            "[{{\"id\":\"{id:04x}\",\"bot\":null,\"presence\":\"available\",\"message\":null}}]"
+        )
+    );
+}
+
+#[test]
+fn shutting_one_server_down_leaves_another_running() {
+    let config = Config {
+        shutdown_countdown: Duration::ZERO,
+        ..Config::default()
+    };
+    let stopping = Server::start(config, "127.0.0.1:0", socket_path().to_str().unwrap()).unwrap();
+    let staying = TestServer::start();
+    let mut leaving = Player::tcp_to(stopping.tcp_addr).sign_up();
+    let mut a = Player::tcp(&staying).sign_up();
+
+    let stopped_addr = stopping.tcp_addr;
+    stopping.shut_down().unwrap();
+    leaving.expect("beef: SERVER SHUT DOWN, BYE!");
+    leaving.expect_hang_up();
+    assert!(TcpStream::connect(stopped_addr).is_err());
+
+    // the other one neither stopped accepting nor told its players anything
+    let b = Player::unix(&staying).sign_up();
+    a.send(b"l");
+    a.expect("beef: USERS ONLINE:");
+    let mut listed = vec![a.line(), a.line()];
+    listed.sort();
+    let mut expected = vec![
+        format!("{:04x} (available)", a.id),
+        format!("{:04x} (available)", b.id),
+    ];
+    expected.sort();
+    assert_eq!(listed, expected);
+    assert!(!staying.server.stores.shutdown.is_requested());
+}